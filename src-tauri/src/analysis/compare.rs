@@ -0,0 +1,378 @@
+use crate::analysis::percent;
+use crate::parser::models::*;
+use hashbrown::HashSet;
+
+pub fn compare_encounters(
+    left_id: &str,
+    left: &Encounter,
+    right_id: &str,
+    right: &Encounter,
+) -> EncounterComparison {
+    let left_players = get_players(left);
+    let right_players = get_players(right);
+
+    let mut players = Vec::new();
+    let mut matched_left: HashSet<&str> = HashSet::new();
+    let mut matched_right: HashSet<&str> = HashSet::new();
+
+    // same character first, then pair whoever is left over by class in damage order
+    for left_player in &left_players {
+        if let Some(right_player) = right_players.iter().find(|p| p.name == left_player.name) {
+            matched_left.insert(&left_player.name);
+            matched_right.insert(&right_player.name);
+            players.push(compare_players("name", left_player, left, right_player, right));
+        }
+    }
+
+    for left_player in &left_players {
+        if matched_left.contains(left_player.name.as_str()) {
+            continue;
+        }
+        if let Some(right_player) = right_players.iter().find(|p| {
+            p.class_id == left_player.class_id && !matched_right.contains(p.name.as_str())
+        }) {
+            matched_left.insert(&left_player.name);
+            matched_right.insert(&right_player.name);
+            players.push(compare_players("class", left_player, left, right_player, right));
+        }
+    }
+
+    players.sort_by(|a, b| b.right.damage.cmp(&a.right.damage));
+
+    EncounterComparison {
+        left: get_compared_encounter(left_id, left),
+        right: get_compared_encounter(right_id, right),
+        players,
+        unmatched_left: left_players
+            .iter()
+            .filter(|p| !matched_left.contains(p.name.as_str()))
+            .map(|p| p.name.clone())
+            .collect(),
+        unmatched_right: right_players
+            .iter()
+            .filter(|p| !matched_right.contains(p.name.as_str()))
+            .map(|p| p.name.clone())
+            .collect(),
+    }
+}
+
+fn get_players(encounter: &Encounter) -> Vec<&EncounterEntity> {
+    let mut players: Vec<&EncounterEntity> = encounter
+        .entities
+        .values()
+        .filter(|e| e.entity_type == EntityType::PLAYER && e.class_id > 0)
+        .collect();
+    players.sort_by(|a, b| b.damage_stats.damage_dealt.cmp(&a.damage_stats.damage_dealt));
+    players
+}
+
+fn get_compared_encounter(id: &str, encounter: &Encounter) -> ComparedEncounter {
+    ComparedEncounter {
+        id: id.to_string(),
+        boss_name: encounter.current_boss_name.clone(),
+        difficulty: encounter.difficulty.clone(),
+        raid: encounter.raid.clone(),
+        gate: encounter.gate,
+        fight_start: encounter.fight_start,
+        duration: encounter.duration,
+        cleared: encounter.cleared,
+        dps: encounter.encounter_damage_stats.dps,
+    }
+}
+
+fn compare_players(
+    matched_by: &str,
+    left_player: &EncounterEntity,
+    left: &Encounter,
+    right_player: &EncounterEntity,
+    right: &Encounter,
+) -> PlayerComparison {
+    let left_snapshot = get_player_snapshot(left_player, left);
+    let right_snapshot = get_player_snapshot(right_player, right);
+    let diff = left_snapshot.diff(&right_snapshot);
+
+    let mut skill_ids: Vec<u32> = left_player
+        .skills
+        .keys()
+        .chain(right_player.skills.keys())
+        .copied()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    skill_ids.sort_unstable();
+
+    let (left_duration, right_duration) = (left.duration, right.duration);
+    let mut skills: Vec<SkillComparison> = skill_ids
+        .into_iter()
+        .map(|id| {
+            let left_skill = left_player.skills.get(&id);
+            let right_skill = right_player.skills.get(&id);
+            let skill = right_skill.or(left_skill).unwrap();
+            let left = left_skill.map(|s| get_skill_snapshot(s, left_player, left_duration));
+            let right = right_skill.map(|s| get_skill_snapshot(s, right_player, right_duration));
+            let diff = match (&left, &right) {
+                (Some(l), Some(r)) => Some(l.diff(r)),
+                _ => None,
+            };
+
+            SkillComparison {
+                id,
+                name: skill.name.clone(),
+                icon: skill.icon.clone(),
+                left,
+                right,
+                diff,
+            }
+        })
+        .collect();
+
+    skills.sort_by(|a, b| {
+        let a_damage = a.right.as_ref().or(a.left.as_ref()).map_or(0, |s| s.damage);
+        let b_damage = b.right.as_ref().or(b.left.as_ref()).map_or(0, |s| s.damage);
+        b_damage.cmp(&a_damage)
+    });
+
+    PlayerComparison {
+        matched_by: matched_by.to_string(),
+        left_name: left_player.name.clone(),
+        right_name: right_player.name.clone(),
+        class: right_player.class.clone(),
+        left: left_snapshot,
+        right: right_snapshot,
+        diff,
+        skills,
+    }
+}
+
+fn get_player_snapshot(player: &EncounterEntity, encounter: &Encounter) -> PlayerSnapshot {
+    let stats = &player.damage_stats;
+    let damage = stats.damage_dealt;
+
+    PlayerSnapshot {
+        damage,
+        dps: stats.dps,
+        damage_percent: percent(damage, encounter.encounter_damage_stats.total_damage_dealt),
+        crit_rate: percent(player.skill_stats.crits, player.skill_stats.hits),
+        back_attack_rate: percent(player.skill_stats.back_attacks, player.skill_stats.hits),
+        front_attack_rate: percent(player.skill_stats.front_attacks, player.skill_stats.hits),
+        buffed_by_support_percent: percent(stats.buffed_by_support, damage),
+        buffed_by_identity_percent: percent(stats.buffed_by_identity, damage),
+        buffed_by_hat_percent: percent(stats.buffed_by_hat, damage),
+        debuffed_by_support_percent: percent(stats.debuffed_by_support, damage),
+        rdps_damage_received: stats.rdps_damage_received,
+        rdps_damage_received_support: stats.rdps_damage_received_support,
+        rdps_damage_given: stats.rdps_damage_given,
+        casts: player.skill_stats.casts,
+        hits: player.skill_stats.hits,
+        deaths: stats.deaths,
+    }
+}
+
+fn get_skill_snapshot(skill: &Skill, player: &EncounterEntity, duration: i64) -> SkillSnapshot {
+    let minutes = duration as f64 / 60_000.0;
+    let casts_per_minute = if minutes > 0.0 {
+        skill.casts as f64 / minutes
+    } else {
+        0.0
+    };
+
+    let mut cast_log = skill.cast_log.clone();
+    cast_log.sort_unstable();
+    let average_cast_interval = if cast_log.len() > 1 {
+        let total = (cast_log[cast_log.len() - 1] - cast_log[0]) as f64;
+        total / (cast_log.len() - 1) as f64 / 1000.0
+    } else {
+        0.0
+    };
+
+    SkillSnapshot {
+        damage: skill.total_damage,
+        dps: skill.dps,
+        damage_percent: percent(skill.total_damage, player.damage_stats.damage_dealt),
+        max_damage: skill.max_damage,
+        casts: skill.casts,
+        hits: skill.hits,
+        crit_rate: percent(skill.crits, skill.hits),
+        buffed_by_support_percent: percent(skill.buffed_by_support, skill.total_damage),
+        buffed_by_identity_percent: percent(skill.buffed_by_identity, skill.total_damage),
+        debuffed_by_support_percent: percent(skill.debuffed_by_support, skill.total_damage),
+        rdps_damage_received: skill.rdps_damage_received,
+        rdps_damage_given: skill.rdps_damage_given,
+        casts_per_minute,
+        average_cast_interval,
+    }
+}
+
+impl PlayerSnapshot {
+    fn diff(&self, other: &Self) -> Self {
+        Self {
+            damage: other.damage - self.damage,
+            dps: other.dps - self.dps,
+            damage_percent: other.damage_percent - self.damage_percent,
+            crit_rate: other.crit_rate - self.crit_rate,
+            back_attack_rate: other.back_attack_rate - self.back_attack_rate,
+            front_attack_rate: other.front_attack_rate - self.front_attack_rate,
+            buffed_by_support_percent: other.buffed_by_support_percent
+                - self.buffed_by_support_percent,
+            buffed_by_identity_percent: other.buffed_by_identity_percent
+                - self.buffed_by_identity_percent,
+            buffed_by_hat_percent: other.buffed_by_hat_percent - self.buffed_by_hat_percent,
+            debuffed_by_support_percent: other.debuffed_by_support_percent
+                - self.debuffed_by_support_percent,
+            rdps_damage_received: other.rdps_damage_received - self.rdps_damage_received,
+            rdps_damage_received_support: other.rdps_damage_received_support
+                - self.rdps_damage_received_support,
+            rdps_damage_given: other.rdps_damage_given - self.rdps_damage_given,
+            casts: other.casts - self.casts,
+            hits: other.hits - self.hits,
+            deaths: other.deaths - self.deaths,
+        }
+    }
+}
+
+impl SkillSnapshot {
+    fn diff(&self, other: &Self) -> Self {
+        Self {
+            damage: other.damage - self.damage,
+            dps: other.dps - self.dps,
+            damage_percent: other.damage_percent - self.damage_percent,
+            max_damage: other.max_damage - self.max_damage,
+            casts: other.casts - self.casts,
+            hits: other.hits - self.hits,
+            crit_rate: other.crit_rate - self.crit_rate,
+            buffed_by_support_percent: other.buffed_by_support_percent
+                - self.buffed_by_support_percent,
+            buffed_by_identity_percent: other.buffed_by_identity_percent
+                - self.buffed_by_identity_percent,
+            debuffed_by_support_percent: other.debuffed_by_support_percent
+                - self.debuffed_by_support_percent,
+            rdps_damage_received: other.rdps_damage_received - self.rdps_damage_received,
+            rdps_damage_given: other.rdps_damage_given - self.rdps_damage_given,
+            casts_per_minute: other.casts_per_minute - self.casts_per_minute,
+            average_cast_interval: other.average_cast_interval - self.average_cast_interval,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(name: &str, class_id: u32, damage: i64, skills: &[(u32, i64)]) -> EncounterEntity {
+        EncounterEntity {
+            name: name.to_string(),
+            entity_type: EntityType::PLAYER,
+            class_id,
+            damage_stats: DamageStats {
+                damage_dealt: damage,
+                dps: damage / 10,
+                ..Default::default()
+            },
+            skills: skills
+                .iter()
+                .map(|&(id, total_damage)| {
+                    let skill = Skill {
+                        id,
+                        total_damage,
+                        ..Default::default()
+                    };
+                    (id, skill)
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn encounter(players: Vec<EncounterEntity>) -> Encounter {
+        let mut encounter = Encounter {
+            duration: 10_000,
+            ..Default::default()
+        };
+        encounter.encounter_damage_stats.total_damage_dealt =
+            players.iter().map(|p| p.damage_stats.damage_dealt).sum();
+        for p in players {
+            encounter.entities.insert(p.name.clone(), p);
+        }
+        encounter
+    }
+
+    #[test]
+    fn diff_is_right_minus_left() {
+        let left = encounter(vec![player("a", 102, 1000, &[(1, 1000)])]);
+        let right = encounter(vec![player("a", 102, 1500, &[(1, 1500)])]);
+
+        let comparison = compare_encounters("1", &left, "2", &right);
+        assert_eq!(comparison.players.len(), 1);
+        let compared = &comparison.players[0];
+        assert_eq!(compared.diff.damage, 500);
+        assert_eq!(compared.diff.dps, 50);
+        assert_eq!(compared.skills[0].diff.as_ref().unwrap().damage, 500);
+
+        let reversed = compare_encounters("2", &right, "1", &left);
+        assert_eq!(reversed.players[0].diff.damage, -500);
+        assert_eq!(reversed.players[0].diff.dps, -50);
+    }
+
+    #[test]
+    fn zero_baseline_percent_is_zero() {
+        let left = encounter(vec![player("a", 102, 0, &[(1, 0)])]);
+        let right = encounter(vec![player("a", 102, 1000, &[(1, 1000)])]);
+
+        let comparison = compare_encounters("1", &left, "2", &right);
+        let compared = &comparison.players[0];
+        assert_eq!(compared.left.damage_percent, 0.0);
+        assert_eq!(compared.left.crit_rate, 0.0);
+        assert_eq!(compared.left.buffed_by_support_percent, 0.0);
+        assert_eq!(compared.right.damage_percent, 100.0);
+        assert_eq!(compared.diff.damage_percent, 100.0);
+
+        let skill = &compared.skills[0];
+        assert_eq!(skill.left.as_ref().unwrap().damage_percent, 0.0);
+        assert!(skill.diff.as_ref().unwrap().damage_percent.is_finite());
+    }
+
+    #[test]
+    fn pairs_by_name_then_class() {
+        let left = encounter(vec![
+            player("a", 102, 1000, &[]),
+            player("b", 204, 800, &[]),
+        ]);
+        let right = encounter(vec![
+            player("a", 102, 1200, &[]),
+            player("c", 204, 900, &[]),
+        ]);
+
+        let comparison = compare_encounters("1", &left, "2", &right);
+        assert_eq!(comparison.players.len(), 2);
+        assert_eq!(comparison.players[0].matched_by, "name");
+        assert_eq!(comparison.players[1].matched_by, "class");
+        assert_eq!(comparison.players[1].left_name, "b");
+        assert_eq!(comparison.players[1].right_name, "c");
+        assert!(comparison.unmatched_left.is_empty());
+        assert!(comparison.unmatched_right.is_empty());
+    }
+
+    #[test]
+    fn entities_missing_on_one_side_are_unmatched() {
+        let left = encounter(vec![
+            player("a", 102, 1000, &[(1, 600), (2, 400)]),
+            player("b", 204, 800, &[]),
+        ]);
+        let right = encounter(vec![
+            player("a", 102, 1200, &[(1, 1200)]),
+            player("c", 305, 900, &[]),
+        ]);
+
+        let comparison = compare_encounters("1", &left, "2", &right);
+        assert_eq!(comparison.players.len(), 1);
+        assert_eq!(comparison.unmatched_left, vec!["b".to_string()]);
+        assert_eq!(comparison.unmatched_right, vec!["c".to_string()]);
+
+        let skills = &comparison.players[0].skills;
+        assert_eq!(skills.len(), 2);
+        let missing = skills.iter().find(|s| s.id == 2).unwrap();
+        assert!(missing.left.is_some());
+        assert!(missing.right.is_none());
+        assert!(missing.diff.is_none());
+    }
+}
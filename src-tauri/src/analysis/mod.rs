@@ -0,0 +1,58 @@
+pub mod compare;
+pub mod progression;
+pub mod ranking;
+pub mod roster;
+pub mod sessions;
+pub mod trends;
+pub mod weekly;
+
+use crate::parser::models::BossHpLog;
+use flate2::read::GzDecoder;
+use hashbrown::HashMap;
+use rusqlite::types::ValueRef;
+use serde::de::DeserializeOwned;
+use std::io::Read;
+
+// skills and damage stats are gzipped json since 1.13.5, plain json before that
+pub(crate) fn read_json_column<T: DeserializeOwned + Default>(value: ValueRef) -> T {
+    match value {
+        ValueRef::Blob(bytes) => {
+            let mut decompress = GzDecoder::new(bytes);
+            let mut json = String::new();
+            if decompress.read_to_string(&mut json).is_err() {
+                return T::default();
+            }
+            serde_json::from_str(&json).unwrap_or_default()
+        }
+        ValueRef::Text(text) => serde_json::from_slice(text).unwrap_or_default(),
+        _ => T::default(),
+    }
+}
+
+// bosses of multi boss gates count as phases, in the order they showed up. returns the
+// phase of the current boss, or of the last boss seen when the current one has no hp log
+pub(crate) fn get_phase_boss<'a>(
+    boss_hp_log: &'a HashMap<String, Vec<BossHpLog>>,
+    current_boss: &str,
+) -> Option<(u32, &'a String, &'a Vec<BossHpLog>)> {
+    let mut bosses: Vec<(&String, &Vec<BossHpLog>)> = boss_hp_log
+        .iter()
+        .filter(|(_, log)| !log.is_empty())
+        .collect();
+    bosses.sort_by_key(|(_, log)| log[0].time);
+
+    let index = bosses
+        .iter()
+        .position(|(name, _)| name.as_str() == current_boss)
+        .or(bosses.len().checked_sub(1))?;
+    let (name, log) = bosses[index];
+    Some((index as u32 + 1, name, log))
+}
+
+pub(crate) fn percent(value: i64, total: i64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        value as f64 / total as f64 * 100.0
+    }
+}
@@ -0,0 +1,197 @@
+use crate::analysis::{get_phase_boss, read_json_column};
+use crate::parser::models::*;
+use chrono::{DateTime, Duration, Local};
+use hashbrown::HashMap;
+use rusqlite::{params, Connection};
+
+// pulls before 6am count towards the previous night
+const NIGHT_START_OFFSET_H: i64 = 6;
+
+struct Pull {
+    boss: String,
+    difficulty: Option<String>,
+    raid: Option<String>,
+    gate: Option<u32>,
+    prog_pull: ProgPull,
+}
+
+// progression for every boss and difficulty that has pulls saved but has never been cleared
+pub fn get_progression(
+    conn: &Connection,
+    boss: Option<&str>,
+) -> Result<Vec<BossProgression>, rusqlite::Error> {
+    let mut stmt = conn.prepare_cached(
+        "
+    SELECT ep.id,
+        ep.current_boss,
+        ep.difficulty,
+        ep.raid,
+        ep.gate,
+        ep.fight_start,
+        ep.duration,
+        e.boss_hp_log,
+        e.misc
+    FROM encounter_preview ep
+    JOIN encounter e USING (id)
+    WHERE NOT ep.cleared
+        AND ep.deleted_at IS NULL
+        AND (?1 IS NULL OR ep.current_boss = ?1)
+        AND NOT EXISTS (
+            SELECT 1
+            FROM encounter_preview c
+            WHERE c.cleared
+                AND c.deleted_at IS NULL
+                AND c.current_boss = ep.current_boss
+                AND c.difficulty IS ep.difficulty
+        )
+    ORDER BY ep.fight_start
+    ",
+    )?;
+    let mut deaths_stmt = conn.prepare_cached(
+        "
+    SELECT damage_stats
+    FROM all_entities
+    WHERE encounter_id = ? AND entity_type = 'PLAYER'
+    ",
+    )?;
+
+    let mut pulls: Vec<Pull> = Vec::new();
+    let mut rows = stmt.query(params![boss])?;
+    while let Some(row) = rows.next()? {
+        let encounter_id: i64 = row.get(0)?;
+        let boss: String = row.get(1)?;
+
+        let mut boss_hp_log: HashMap<String, Vec<BossHpLog>> = read_json_column(row.get_ref(7)?);
+        if boss_hp_log.is_empty() {
+            // logs from before 1.13.5 kept the hp log in misc
+            let misc: Option<EncounterMisc> = read_json_column(row.get_ref(8)?);
+            boss_hp_log = misc.and_then(|m| m.boss_hp_log).unwrap_or_default();
+        }
+        let (hp_percent, phase, phase_boss) = get_lowest_hp(&boss_hp_log, &boss);
+
+        let deaths: i64 = deaths_stmt
+            .query_map(params![encounter_id], |row| {
+                let damage_stats: DamageStats = read_json_column(row.get_ref(0)?);
+                Ok(damage_stats.deaths)
+            })?
+            .flatten()
+            .sum();
+
+        pulls.push(Pull {
+            boss,
+            difficulty: row.get(2)?,
+            raid: row.get(3)?,
+            gate: row.get(4)?,
+            prog_pull: ProgPull {
+                encounter_id,
+                fight_start: row.get(5)?,
+                duration: row.get(6)?,
+                hp_percent,
+                phase,
+                phase_boss,
+                deaths,
+                ..Default::default()
+            },
+        });
+    }
+
+    let mut progression: Vec<BossProgression> = Vec::new();
+    for pull in pulls {
+        let index = match progression
+            .iter()
+            .position(|p| p.boss == pull.boss && p.difficulty == pull.difficulty)
+        {
+            Some(index) => index,
+            None => {
+                progression.push(BossProgression {
+                    boss: pull.boss,
+                    difficulty: pull.difficulty,
+                    raid: pull.raid,
+                    gate: pull.gate,
+                    best_hp_percent: 100.0,
+                    ..Default::default()
+                });
+                progression.len() - 1
+            }
+        };
+        add_pull(&mut progression[index], pull.prog_pull);
+    }
+
+    for boss_progression in progression.iter_mut() {
+        let pulls = boss_progression.pulls.max(1) as f64;
+        boss_progression.average_deaths = boss_progression.total_deaths as f64 / pulls;
+    }
+
+    Ok(progression)
+}
+
+fn add_pull(progression: &mut BossProgression, mut pull: ProgPull) {
+    progression.pulls += 1;
+    pull.pull = progression.pulls;
+    progression.total_deaths += pull.deaths;
+    progression.total_time += pull.duration;
+    if pull.hp_percent < progression.best_hp_percent || progression.best_encounter_id.is_none() {
+        progression.best_hp_percent = pull.hp_percent;
+        progression.best_phase = pull.phase;
+        progression.best_encounter_id = Some(pull.encounter_id);
+    }
+
+    let night = get_night(pull.fight_start);
+    match progression.nights.last_mut() {
+        Some(last) if last.night == night => {
+            last.pulls += 1;
+            last.combat_time += pull.duration;
+            last.last_pull_end = pull.fight_start + pull.duration;
+            last.best_hp_percent = last.best_hp_percent.min(pull.hp_percent);
+            last.deaths += pull.deaths;
+        }
+        _ => progression.nights.push(ProgNight {
+            night,
+            pulls: 1,
+            combat_time: pull.duration,
+            first_pull_start: pull.fight_start,
+            last_pull_end: pull.fight_start + pull.duration,
+            best_hp_percent: pull.hp_percent,
+            deaths: pull.deaths,
+        }),
+    }
+
+    progression.curve.push(pull);
+}
+
+// lowest hp reached on the furthest boss of the gate
+fn get_lowest_hp(
+    boss_hp_log: &HashMap<String, Vec<BossHpLog>>,
+    current_boss: &str,
+) -> (f64, u32, String) {
+    let Some((phase, name, log)) = get_phase_boss(boss_hp_log, current_boss) else {
+        return (100.0, 0, current_boss.to_string());
+    };
+
+    let lowest = if log.iter().any(|l| l.p > 0.0) {
+        log.iter().map(|l| l.p as f64).fold(1.0, f64::min)
+    } else {
+        // old logs have no percentage, use the highest hp seen as the bar size
+        let max_hp = log.iter().map(|l| l.hp).max().unwrap_or_default();
+        let min_hp = log.iter().map(|l| l.hp).min().unwrap_or_default();
+        if max_hp > 0 {
+            min_hp as f64 / max_hp as f64
+        } else {
+            1.0
+        }
+    };
+    (lowest * 100.0, phase, name.clone())
+}
+
+// local date the pull counts towards, as ms of that day's local midnight
+fn get_night(fight_start: i64) -> i64 {
+    let Some(date) = DateTime::from_timestamp_millis(fight_start) else {
+        return 0;
+    };
+    let local = date.with_timezone(&Local) - Duration::hours(NIGHT_START_OFFSET_H);
+    local
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .and_then(|night| night.and_local_timezone(Local).earliest())
+        .map_or(0, |night| night.timestamp_millis())
+}
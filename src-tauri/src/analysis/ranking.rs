@@ -0,0 +1,84 @@
+use crate::parser::models::*;
+use rusqlite::{params, Connection};
+
+// ranks every player of an encounter against all local logs of the same boss, difficulty,
+// class and spec, optionally only against logs within `gear_score_band` item levels
+pub fn get_encounter_rankings(
+    conn: &Connection,
+    encounter_id: &str,
+    gear_score_band: Option<f32>,
+) -> Result<Vec<PlayerRanking>, rusqlite::Error> {
+    let mut stmt = conn.prepare_cached(
+        "
+    WITH target AS (
+        SELECT en.name,
+            en.class_id,
+            en.class,
+            en.spec,
+            en.gear_score,
+            en.dps,
+            ep.current_boss,
+            ep.difficulty
+        FROM all_entities en
+        JOIN encounter_preview ep ON ep.id = en.encounter_id
+        WHERE en.encounter_id = ?1 AND en.entity_type = 'PLAYER' AND en.class_id > 0
+    ),
+    counted AS (
+        SELECT t.*,
+            (SELECT COUNT(*)
+                FROM all_entities o
+                JOIN encounter_preview op ON op.id = o.encounter_id
+                WHERE op.current_boss = t.current_boss
+                    AND op.difficulty IS t.difficulty
+                    AND op.deleted_at IS NULL
+                    AND o.entity_type = 'PLAYER'
+                    AND o.class_id = t.class_id
+                    AND (t.spec IS NULL OR o.spec IS t.spec)
+                    AND (?2 IS NULL OR ABS(o.gear_score - t.gear_score) <= ?2)
+            ) AS total,
+            (SELECT COUNT(*)
+                FROM all_entities o
+                JOIN encounter_preview op ON op.id = o.encounter_id
+                WHERE op.current_boss = t.current_boss
+                    AND op.difficulty IS t.difficulty
+                    AND op.deleted_at IS NULL
+                    AND o.entity_type = 'PLAYER'
+                    AND o.class_id = t.class_id
+                    AND (t.spec IS NULL OR o.spec IS t.spec)
+                    AND (?2 IS NULL OR ABS(o.gear_score - t.gear_score) <= ?2)
+                    AND o.dps > t.dps
+            ) AS better
+        FROM target t
+    )
+    SELECT name,
+        class_id,
+        class,
+        spec,
+        gear_score,
+        dps,
+        better + 1 AS rank,
+        total,
+        CASE WHEN total > 1 THEN 100.0 * (total - better - 1) / (total - 1) ELSE 100.0 END AS percentile
+    FROM counted
+    ORDER BY dps DESC
+    ",
+    )?;
+
+    let rankings = stmt
+        .query_map(params![encounter_id, gear_score_band], |row| {
+            Ok(PlayerRanking {
+                name: row.get(0)?,
+                class_id: row.get(1)?,
+                class: row.get(2).unwrap_or_default(),
+                spec: row.get(3)?,
+                gear_score: row.get(4)?,
+                dps: row.get(5)?,
+                rank: row.get(6)?,
+                total: row.get(7)?,
+                percentile: row.get(8)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(rankings)
+}
@@ -0,0 +1,87 @@
+use crate::analysis::weekly::get_weekly_reset_start;
+use crate::parser::models::*;
+use hashbrown::HashSet;
+use rusqlite::{params, Connection};
+
+pub fn get_roster(
+    conn: &Connection,
+    local_info: &LocalInfo,
+    region: Option<&str>,
+    now: i64,
+) -> Result<Vec<RosterCharacter>, rusqlite::Error> {
+    let reset_start = get_weekly_reset_start(now, region);
+
+    let mut clears_stmt = conn.prepare_cached(
+        "
+    SELECT raid, gate, difficulty, MAX(id), MAX(fight_start)
+    FROM encounter_preview
+    WHERE local_player = ? AND cleared AND raid IS NOT NULL AND fight_start >= ?
+        AND deleted_at IS NULL
+    GROUP BY raid, gate, difficulty
+    ORDER BY raid, gate
+    ",
+    )?;
+    let mut history_stmt = conn.prepare_cached(
+        "
+    SELECT DISTINCT raid, gate, difficulty
+    FROM encounter_preview
+    WHERE local_player = ? AND cleared AND raid IS NOT NULL AND fight_start < ?
+        AND deleted_at IS NULL
+    ORDER BY raid, gate
+    ",
+    )?;
+
+    let mut roster = Vec::new();
+    for (character_id, local_player) in local_info.local_players.iter() {
+        let clears = clears_stmt
+            .query_map(params![local_player.name, reset_start], |row| {
+                Ok(RosterClear {
+                    raid: row.get(0)?,
+                    gate: row.get(1)?,
+                    difficulty: row.get(2).unwrap_or_default(),
+                    encounter_id: row.get(3)?,
+                    fight_start: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let cleared: HashSet<(&str, u32, &str)> = clears
+            .iter()
+            .map(|c| (c.raid.as_str(), c.gate, c.difficulty.as_str()))
+            .collect();
+        let pending = history_stmt
+            .query_map(params![local_player.name, reset_start], |row| {
+                Ok(RosterClear {
+                    raid: row.get(0)?,
+                    gate: row.get(1)?,
+                    difficulty: row.get(2).unwrap_or_default(),
+                    ..Default::default()
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|c| !cleared.contains(&(c.raid.as_str(), c.gate, c.difficulty.as_str())))
+            .collect();
+
+        roster.push(RosterCharacter {
+            character_id: *character_id,
+            name: local_player.name.clone(),
+            class_id: local_player.class_id,
+            class: local_player.class.clone(),
+            gear_score: local_player.gear_score,
+            last_seen: local_player.last_seen,
+            count: local_player.count,
+            clears,
+            pending,
+        });
+    }
+
+    roster.sort_by(|a, b| {
+        b.gear_score
+            .partial_cmp(&a.gear_score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    Ok(roster)
+}
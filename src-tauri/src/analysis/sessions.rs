@@ -0,0 +1,202 @@
+use crate::analysis::read_json_column;
+use crate::parser::models::*;
+use hashbrown::HashMap;
+use rusqlite::{params, Connection};
+
+pub fn get_sessions(
+    conn: &Connection,
+    page: i32,
+    page_size: i32,
+) -> Result<Vec<RaidSession>, rusqlite::Error> {
+    let mut stmt = conn.prepare_cached(
+        "
+    SELECT session_id,
+        MIN(fight_start),
+        MAX(fight_start + duration),
+        COUNT(*),
+        SUM(duration),
+        SUM(cleared),
+        SUM(NOT cleared AND difficulty IS NOT NULL AND difficulty != '')
+    FROM encounter_preview
+    WHERE session_id IS NOT NULL AND deleted_at IS NULL
+    GROUP BY session_id
+    ORDER BY MIN(fight_start) DESC
+    LIMIT ? OFFSET ?
+    ",
+    )?;
+
+    // damage is rebuilt from the dps column so the blobs stay unread, deaths here are pulls
+    // the player ended dead, revives are only known from damage stats in get_session
+    let mut players_stmt = conn.prepare_cached(
+        "
+    WITH page AS (
+        SELECT session_id
+        FROM encounter_preview
+        WHERE session_id IS NOT NULL AND deleted_at IS NULL
+        GROUP BY session_id
+        ORDER BY MIN(fight_start) DESC
+        LIMIT ? OFFSET ?
+    )
+    SELECT ep.session_id,
+        en.name,
+        en.class_id,
+        en.class,
+        COUNT(*),
+        SUM(ep.cleared),
+        SUM(ep.duration),
+        COALESCE(SUM(en.dps * MAX(ep.duration / 1000, 1)), 0),
+        COALESCE(SUM(en.is_dead), 0)
+    FROM all_entities en
+    JOIN encounter_preview ep ON ep.id = en.encounter_id
+    WHERE ep.session_id IN (SELECT session_id FROM page)
+        AND ep.deleted_at IS NULL
+        AND en.entity_type = 'PLAYER' AND en.class_id > 0
+    GROUP BY ep.session_id, en.name
+    ",
+    )?;
+
+    let offset = (page - 1).max(0) * page_size;
+    let mut players: HashMap<i64, Vec<SessionPlayer>> = HashMap::new();
+    let mut rows = players_stmt.query(params![page_size, offset])?;
+    while let Some(row) = rows.next()? {
+        let combat_time: i64 = row.get(6)?;
+        let damage: i64 = row.get(7)?;
+        players.entry(row.get(0)?).or_default().push(SessionPlayer {
+            name: row.get(1)?,
+            class_id: row.get(2)?,
+            class: row.get(3).unwrap_or_default(),
+            encounters: row.get(4)?,
+            clears: row.get(5)?,
+            combat_time,
+            damage,
+            dps: damage / (combat_time / 1000).max(1),
+            deaths: row.get(8)?,
+            ..Default::default()
+        });
+    }
+
+    let mut sessions = stmt
+        .query_map(params![page_size, offset], |row| {
+            let start: i64 = row.get(1)?;
+            let end: i64 = row.get(2)?;
+            let combat_time: i64 = row.get(4)?;
+            Ok(RaidSession {
+                id: row.get(0)?,
+                start,
+                end,
+                encounters: row.get(3)?,
+                total_time: end - start,
+                combat_time,
+                downtime: (end - start - combat_time).max(0),
+                gates_cleared: row.get(5)?,
+                wipes: row.get(6)?,
+                ..Default::default()
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    for session in sessions.iter_mut() {
+        session.players = players.remove(&session.id).unwrap_or_default();
+        session.players.sort_by(|a, b| b.damage.cmp(&a.damage));
+    }
+
+    Ok(sessions)
+}
+
+pub fn get_session(
+    conn: &Connection,
+    session_id: i64,
+) -> Result<Option<RaidSession>, rusqlite::Error> {
+    let mut pulls_stmt = conn.prepare_cached(
+        "
+    SELECT id, current_boss, difficulty, fight_start, duration, cleared
+    FROM encounter_preview
+    WHERE session_id = ? AND deleted_at IS NULL
+    ORDER BY fight_start
+    ",
+    )?;
+    let mut players_stmt = conn.prepare_cached(
+        "
+    SELECT en.name, en.class_id, en.class, en.damage_stats, ep.duration, ep.cleared
+    FROM all_entities en
+    JOIN encounter_preview ep ON ep.id = en.encounter_id
+    WHERE ep.session_id = ? AND ep.deleted_at IS NULL
+        AND en.entity_type = 'PLAYER' AND en.class_id > 0
+    ",
+    )?;
+
+    let pulls = pulls_stmt
+        .query_map(params![session_id], |row| {
+            Ok(SessionPull {
+                encounter_id: row.get(0)?,
+                boss: row.get(1)?,
+                difficulty: row.get(2)?,
+                fight_start: row.get(3)?,
+                duration: row.get(4)?,
+                cleared: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    if pulls.is_empty() {
+        return Ok(None);
+    }
+
+    let mut session = RaidSession {
+        id: session_id,
+        encounters: pulls.len() as i64,
+        ..Default::default()
+    };
+    if let (Some(first), Some(last)) = (pulls.first(), pulls.last()) {
+        session.start = first.fight_start;
+        session.end = last.fight_start + last.duration;
+    }
+    for pull in pulls.iter() {
+        session.combat_time += pull.duration;
+        if pull.cleared {
+            session.gates_cleared += 1;
+        } else if pull.difficulty.as_deref().is_some_and(|d| !d.is_empty()) {
+            session.wipes += 1;
+        }
+    }
+    session.total_time = session.end - session.start;
+    session.downtime = (session.total_time - session.combat_time).max(0);
+
+    let mut players: HashMap<String, SessionPlayer> = HashMap::new();
+    let mut rows = players_stmt.query(params![session_id])?;
+    while let Some(row) = rows.next()? {
+        let name: String = row.get(0)?;
+        let damage_stats: DamageStats = read_json_column(row.get_ref(3)?);
+        let duration: i64 = row.get(4)?;
+
+        let player = players
+            .entry(name.clone())
+            .or_insert_with(|| SessionPlayer {
+                name,
+                ..Default::default()
+            });
+        player.class_id = row.get(1)?;
+        player.class = row.get(2).unwrap_or_default();
+        player.encounters += 1;
+        if row.get::<_, bool>(5)? {
+            player.clears += 1;
+        }
+        player.combat_time += duration;
+        player.damage += damage_stats.damage_dealt;
+        player.rdps_damage += damage_stats.damage_dealt - damage_stats.rdps_damage_received
+            + damage_stats.rdps_damage_given;
+        player.deaths += damage_stats.deaths;
+    }
+
+    session.players = players
+        .into_values()
+        .map(|mut player| {
+            let seconds = (player.combat_time / 1000).max(1);
+            player.dps = player.damage / seconds;
+            player.rdps = player.rdps_damage / seconds;
+            player
+        })
+        .collect();
+    session.players.sort_by(|a, b| b.damage.cmp(&a.damage));
+    session.pulls = pulls;
+
+    Ok(Some(session))
+}
@@ -0,0 +1,150 @@
+use crate::analysis::{percent, read_json_column};
+use crate::parser::models::*;
+use chrono::{DateTime, Datelike, Duration};
+use hashbrown::HashMap;
+use rusqlite::{params, Connection};
+
+#[derive(Default)]
+struct WeekTotals {
+    encounters: i64,
+    total_dps: i64,
+    best_dps: i64,
+    gear_score: f32,
+    hits: i64,
+    crits: i64,
+    damage: i64,
+    buffed_by_support: i64,
+    deaths: i64,
+}
+
+pub fn get_character_trends(
+    conn: &Connection,
+    name: &str,
+) -> Result<CharacterTrends, rusqlite::Error> {
+    let mut stmt = conn.prepare_cached(
+        "
+    SELECT en.encounter_id,
+        ep.fight_start,
+        ep.current_boss,
+        ep.difficulty,
+        en.class_id,
+        en.class,
+        en.gear_score,
+        en.dps,
+        en.spec,
+        en.ark_passive_data,
+        en.skill_stats,
+        en.damage_stats
+    FROM all_entities en
+    JOIN encounter_preview ep ON ep.id = en.encounter_id
+    WHERE en.name = ? AND en.entity_type = 'PLAYER' AND en.class_id > 0 AND ep.deleted_at IS NULL
+    ORDER BY ep.fight_start
+    ",
+    )?;
+    let mut rows = stmt.query(params![name])?;
+
+    let mut trends = CharacterTrends {
+        name: name.to_string(),
+        ..Default::default()
+    };
+    // keeps boss order stable by first appearance
+    let mut boss_order: Vec<(String, Option<String>)> = Vec::new();
+    let mut weeks: HashMap<(String, Option<String>), HashMap<i64, WeekTotals>> = HashMap::new();
+    let mut last_spec: Option<(Option<String>, Option<String>)> = None;
+
+    while let Some(row) = rows.next()? {
+        let encounter_id: i64 = row.get(0)?;
+        let fight_start: i64 = row.get(1)?;
+        let boss: String = row.get(2)?;
+        let difficulty: Option<String> = row.get(3)?;
+        let gear_score: f32 = row.get(6).unwrap_or_default();
+        let dps: i64 = row.get(7).unwrap_or_default();
+        let spec: Option<String> = row.get(8).unwrap_or_default();
+        let ark_passive_data: Option<String> = row.get(9).unwrap_or_default();
+        let skill_stats: SkillStats = read_json_column(row.get_ref(10)?);
+        let damage_stats: DamageStats = read_json_column(row.get_ref(11)?);
+
+        trends.class_id = row.get(4)?;
+        trends.class = row.get(5).unwrap_or_default();
+
+        // older logs have no spec info, don't count them as a change
+        if spec.is_some() || ark_passive_data.as_deref().is_some_and(|s| s != "null") {
+            if let Some((previous_spec, previous_ark_passive)) = &last_spec {
+                let ark_passive_changed = *previous_ark_passive != ark_passive_data;
+                if *previous_spec != spec || ark_passive_changed {
+                    trends.spec_changes.push(SpecChange {
+                        encounter_id,
+                        fight_start,
+                        previous_spec: previous_spec.clone(),
+                        spec: spec.clone(),
+                        ark_passive_changed,
+                        ark_passive_data: ark_passive_data
+                            .as_deref()
+                            .and_then(|s| serde_json::from_str(s).ok()),
+                    });
+                }
+            }
+            last_spec = Some((spec, ark_passive_data));
+        }
+
+        let key = (boss, difficulty);
+        if !weeks.contains_key(&key) {
+            boss_order.push(key.clone());
+        }
+        let week = weeks
+            .entry(key)
+            .or_default()
+            .entry(get_week_start(fight_start))
+            .or_default();
+        week.encounters += 1;
+        week.total_dps += dps;
+        week.best_dps = week.best_dps.max(dps);
+        week.gear_score = week.gear_score.max(gear_score);
+        week.hits += skill_stats.hits;
+        week.crits += skill_stats.crits;
+        week.damage += damage_stats.damage_dealt;
+        week.buffed_by_support += damage_stats.buffed_by_support;
+        week.deaths += damage_stats.deaths;
+    }
+
+    for (boss, difficulty) in boss_order {
+        let Some(boss_weeks) = weeks.remove(&(boss.clone(), difficulty.clone())) else {
+            continue;
+        };
+        let mut boss_weeks: Vec<WeeklyTrend> = boss_weeks
+            .into_iter()
+            .map(|(week_start, totals)| WeeklyTrend {
+                week_start,
+                encounters: totals.encounters,
+                average_dps: totals.total_dps / totals.encounters.max(1),
+                best_dps: totals.best_dps,
+                gear_score: totals.gear_score,
+                crit_rate: percent(totals.crits, totals.hits),
+                buffed_by_support_percent: percent(totals.buffed_by_support, totals.damage),
+                deaths: totals.deaths,
+            })
+            .collect();
+        boss_weeks.sort_by_key(|w| w.week_start);
+
+        trends.bosses.push(BossTrend {
+            boss,
+            difficulty,
+            weeks: boss_weeks,
+        });
+    }
+
+    Ok(trends)
+}
+
+// monday 00:00 utc of the week the timestamp falls in
+fn get_week_start(timestamp: i64) -> i64 {
+    let Some(date) = DateTime::from_timestamp_millis(timestamp) else {
+        return 0;
+    };
+    let monday = date.date_naive() - Duration::days(date.weekday().num_days_from_monday() as i64);
+    monday
+        .and_hms_opt(0, 0, 0)
+        .unwrap_or_default()
+        .and_utc()
+        .timestamp_millis()
+}
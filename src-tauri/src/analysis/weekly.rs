@@ -0,0 +1,185 @@
+use crate::parser::models::*;
+use chrono::{DateTime, Datelike, Duration, Timelike, Weekday};
+use rusqlite::{params, Connection};
+
+// windows caps tray tooltips at 128 characters
+const TRAY_TOOLTIP_LIMIT: usize = 127;
+
+// (weekday, hour) in utc when weekly lockouts reset
+fn get_reset_time(region: Option<&str>) -> (Weekday, u32) {
+    match region.map(|r| r.to_uppercase()).as_deref() {
+        // wednesday 06:00 kst
+        Some("KR") | Some("KOREA") => (Weekday::Tue, 21),
+        // na, eu and sa all reset wednesday 10:00 utc
+        _ => (Weekday::Wed, 10),
+    }
+}
+
+// start of the lockout week containing `now`, in ms
+pub fn get_weekly_reset_start(now: i64, region: Option<&str>) -> i64 {
+    let Some(now) = DateTime::from_timestamp_millis(now) else {
+        return 0;
+    };
+    let (reset_weekday, reset_hour) = get_reset_time(region);
+    let days_since_reset = (now.weekday().num_days_from_monday() as i64
+        - reset_weekday.num_days_from_monday() as i64)
+        .rem_euclid(7);
+    let mut reset = (now.date_naive() - Duration::days(days_since_reset))
+        .and_hms_opt(reset_hour, 0, 0)
+        .unwrap_or_default()
+        .and_utc();
+    if days_since_reset == 0 && now.hour() < reset_hour {
+        reset -= Duration::days(7);
+    }
+
+    reset.timestamp_millis()
+}
+
+pub fn get_weekly_clears(
+    conn: &Connection,
+    region: Option<&str>,
+    now: i64,
+) -> Result<WeeklyClears, rusqlite::Error> {
+    let reset_start = get_weekly_reset_start(now, region);
+
+    // gates are grouped by raid and gate when known, by boss name for older logs
+    let mut stmt = conn.prepare_cached(
+        "
+    WITH clears AS (
+        SELECT ep.local_player,
+            ep.current_boss,
+            ep.raid,
+            ep.gate,
+            ep.difficulty,
+            MIN(ep.id) AS first_id,
+            MIN(ep.fight_start) AS first_fight_start,
+            COUNT(*) AS clears
+        FROM encounter_preview ep
+        WHERE ep.cleared AND ep.fight_start >= ?1 AND ep.local_player IS NOT NULL
+            AND ep.deleted_at IS NULL
+        GROUP BY ep.local_player, COALESCE(ep.raid, ep.current_boss), ep.gate, ep.difficulty
+    )
+    SELECT c.local_player,
+        c.current_boss,
+        c.raid,
+        c.gate,
+        c.difficulty,
+        c.first_id,
+        c.first_fight_start,
+        c.clears,
+        NOT EXISTS (
+            SELECT 1
+            FROM encounter_preview p
+            WHERE p.local_player = c.local_player
+                AND p.cleared
+                AND p.deleted_at IS NULL
+                AND p.fight_start < ?1
+                AND p.difficulty IS c.difficulty
+                AND CASE WHEN c.raid IS NULL
+                    THEN p.current_boss = c.current_boss
+                    ELSE p.raid = c.raid AND p.gate = c.gate
+                END
+        ),
+        (SELECT class_id FROM all_entities WHERE encounter_id = c.first_id AND name = c.local_player)
+    FROM clears c
+    ORDER BY c.local_player, c.first_fight_start
+    ",
+    )?;
+
+    let mut characters: Vec<CharacterClears> = Vec::new();
+    let mut rows = stmt.query(params![reset_start])?;
+    while let Some(row) = rows.next()? {
+        let name: String = row.get(0)?;
+        let class_id: u32 = row.get::<_, Option<u32>>(9)?.unwrap_or_default();
+        let clear = WeeklyClear {
+            boss: row.get(1)?,
+            raid: row.get(2)?,
+            gate: row.get(3)?,
+            difficulty: row.get(4).unwrap_or_default(),
+            encounter_id: row.get(5)?,
+            fight_start: row.get(6)?,
+            clears: row.get(7)?,
+            first_clear: row.get(8)?,
+        };
+
+        match characters.last_mut() {
+            Some(character) if character.name == name => character.clears.push(clear),
+            _ => characters.push(CharacterClears {
+                name,
+                class_id,
+                clears: vec![clear],
+            }),
+        }
+    }
+
+    Ok(WeeklyClears {
+        region: region.map(str::to_string),
+        reset_start,
+        next_reset: reset_start + Duration::days(7).num_milliseconds(),
+        characters,
+    })
+}
+
+pub fn get_tray_summary(weekly_clears: &WeeklyClears) -> String {
+    if weekly_clears.characters.is_empty() {
+        return "No clears this week".to_string();
+    }
+
+    let mut summary = "Weekly clears".to_string();
+    for character in weekly_clears.characters.iter() {
+        let line = format!("\n{}: {}", character.name, character.clears.len());
+        if summary.len() + line.len() > TRAY_TOOLTIP_LIMIT {
+            break;
+        }
+        summary.push_str(&line);
+    }
+
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn millis(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> i64 {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+            .timestamp_millis()
+    }
+
+    #[test]
+    fn resets_wednesday_10_utc() {
+        let reset = millis(2026, 10, 14, 10, 0);
+        assert_eq!(get_weekly_reset_start(reset, Some("NA")), reset);
+        assert_eq!(
+            get_weekly_reset_start(millis(2026, 10, 18, 12, 0), Some("EU")),
+            reset
+        );
+        assert_eq!(
+            get_weekly_reset_start(millis(2026, 10, 21, 9, 59), None),
+            reset
+        );
+    }
+
+    #[test]
+    fn before_reset_on_reset_day_is_previous_week() {
+        assert_eq!(
+            get_weekly_reset_start(millis(2026, 10, 14, 9, 59), Some("NA")),
+            millis(2026, 10, 7, 10, 0)
+        );
+    }
+
+    #[test]
+    fn korea_resets_tuesday_21_utc() {
+        let reset = millis(2026, 10, 13, 21, 0);
+        assert_eq!(
+            get_weekly_reset_start(millis(2026, 10, 14, 3, 0), Some("kr")),
+            reset
+        );
+        assert_eq!(
+            get_weekly_reset_start(millis(2026, 10, 13, 20, 59), Some("KR")),
+            millis(2026, 10, 6, 21, 0)
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::info;
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection, DatabaseName};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+pub const ARCHIVE_FILE: &str = "encounters.archive.db";
+
+// entities of archived encounters live in archive.entity, everything else stays in main,
+// all_entities is a temp view over both for queries that span the whole history
+
+// attaches the archive next to the db the connection has open, creating or updating it
+// only the writer calls this, so schema changes never race with reads
+pub fn create_archive(conn: &Connection) -> Result<(), rusqlite::Error> {
+    if is_attached(conn)? {
+        return Ok(());
+    }
+
+    let archive_path = archive_path(conn)?;
+    conn.execute(
+        "ATTACH DATABASE ? AS archive",
+        params![archive_path.to_string_lossy()],
+    )?;
+    // persistent like the main db, archiving never blocks the readers
+    conn.pragma_update_and_check(
+        Some(DatabaseName::Attached("archive")),
+        "journal_mode",
+        "WAL",
+        |row| row.get::<_, String>(0),
+    )?;
+    // same layout as main.entity, minus the foreign key which can't cross databases
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS archive.entity (
+            name TEXT,
+            character_id INTEGER,
+            encounter_id INTEGER NOT NULL,
+            npc_id INTEGER,
+            entity_type TEXT,
+            class_id INTEGER,
+            class TEXT,
+            gear_score REAL,
+            current_hp INTEGER,
+            max_hp INTEGER,
+            is_dead INTEGER,
+            skills TEXT,
+            damage_stats TEXT,
+            dps INTEGER,
+            skill_stats TEXT,
+            last_update INTEGER,
+            engravings TEXT,
+            gear_hash TEXT,
+            spec TEXT,
+            ark_passive_active BOOLEAN,
+            ark_passive_data TEXT,
+            entity_key TEXT NOT NULL,
+            PRIMARY KEY (encounter_id, entity_key)
+        );
+        CREATE INDEX IF NOT EXISTS archive.entity_encounter_id_index
+        ON entity (encounter_id desc);
+        ",
+    )?;
+
+    // columns added to main by later migrations
+    let archive_columns = table_columns(conn, "archive")?;
+    let main_columns = table_columns(conn, "main")?;
+    for (column, column_type) in main_columns.iter() {
+        if !archive_columns.iter().any(|(name, _)| name == column) {
+            conn.execute(
+                &format!(
+                    "ALTER TABLE archive.entity ADD COLUMN {} {}",
+                    column, column_type
+                ),
+                [],
+            )?;
+        }
+    }
+
+    create_view(conn, &main_columns)
+}
+
+// read only attach for the reader pool, the writer creates the archive when the db opens
+pub fn attach_archive(conn: &Connection) -> Result<(), rusqlite::Error> {
+    if is_attached(conn)? {
+        return Ok(());
+    }
+
+    let archive_path = archive_path(conn)?;
+    conn.execute(
+        "ATTACH DATABASE ? AS archive",
+        params![read_only_uri(&archive_path)],
+    )?;
+    create_view(conn, &table_columns(conn, "main")?)
+}
+
+fn is_attached(conn: &Connection) -> Result<bool, rusqlite::Error> {
+    let mut stmt = conn.prepare_cached("SELECT 1 FROM pragma_database_list WHERE name = ?")?;
+    stmt.exists(["archive"])
+}
+
+// temp views live on the connection, so every connection creates its own
+fn create_view(conn: &Connection, columns: &[(String, String)]) -> Result<(), rusqlite::Error> {
+    let columns = column_list(columns);
+    conn.execute(
+        &format!(
+            "
+        CREATE TEMP VIEW IF NOT EXISTS all_entities AS
+        SELECT {0} FROM main.entity
+        UNION ALL
+        SELECT {0} FROM archive.entity
+        ",
+            columns
+        ),
+        [],
+    )?;
+
+    Ok(())
+}
+
+fn archive_path(conn: &Connection) -> Result<PathBuf, rusqlite::Error> {
+    conn.path()
+        .filter(|db_path| !db_path.is_empty())
+        .map(|db_path| Path::new(db_path).with_file_name(ARCHIVE_FILE))
+        .ok_or_else(|| rusqlite::Error::InvalidPath("in memory database".into()))
+}
+
+// sqlite only takes open flags for attached dbs through a uri
+fn read_only_uri(path: &Path) -> String {
+    let path = path
+        .to_string_lossy()
+        .replace('\\', "/")
+        .replace('%', "%25")
+        .replace('?', "%3f")
+        .replace('#', "%23");
+    if path.starts_with('/') {
+        format!("file:{}?mode=ro", path)
+    } else {
+        // windows drive paths
+        format!("file:/{}?mode=ro", path)
+    }
+}
+
+fn table_columns(
+    conn: &Connection,
+    schema: &str,
+) -> Result<Vec<(String, String)>, rusqlite::Error> {
+    let mut stmt = conn.prepare_cached("SELECT name, type FROM pragma_table_info('entity', ?)")?;
+    let columns = stmt
+        .query_map([schema], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<(String, String)>, _>>()?;
+    Ok(columns)
+}
+
+fn column_list(columns: &[(String, String)]) -> String {
+    columns
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub fn is_archived(conn: &Connection, encounter_id: &str) -> bool {
+    conn.query_row(
+        "SELECT archived FROM encounter_preview WHERE id = ?",
+        [encounter_id],
+        |row| row.get(0),
+    )
+    .unwrap_or(false)
+}
+
+// moves the entities of encounters older than the cutoff into the archive
+// favorites and trashed logs are left alone, returns how many encounters were moved
+pub fn archive_encounters(
+    conn: &mut Connection,
+    older_than: i64,
+) -> Result<usize, rusqlite::Error> {
+    create_archive(conn)?;
+    let columns = column_list(&table_columns(conn, "main")?);
+
+    let tx = conn.transaction()?;
+    // blob bytes before recompressing and how many of them it saved, logged below
+    let mut blob_bytes = 0;
+    let mut saved_bytes = 0;
+    let ids = {
+        let mut ids_stmt = tx.prepare(
+            "
+        SELECT id
+        FROM encounter_preview
+        WHERE fight_start < ? AND NOT archived AND NOT favorite AND deleted_at IS NULL
+        ",
+        )?;
+        let ids = ids_stmt
+            .query_map([older_than], |row| row.get(0))?
+            .collect::<Result<Vec<i64>, _>>()?;
+
+        let mut copy_stmt = tx.prepare(&format!(
+            "INSERT OR REPLACE INTO archive.entity ({0}) SELECT {0} FROM main.entity WHERE encounter_id = ?",
+            columns
+        ))?;
+        let mut blobs_stmt = tx.prepare(
+            "SELECT rowid, skills, damage_stats FROM archive.entity WHERE encounter_id = ?",
+        )?;
+        let mut recompress_stmt =
+            tx.prepare("UPDATE archive.entity SET skills = ?, damage_stats = ? WHERE rowid = ?")?;
+        let mut delete_stmt = tx.prepare("DELETE FROM main.entity WHERE encounter_id = ?")?;
+        let mut archived_stmt =
+            tx.prepare("UPDATE encounter_preview SET archived = 1 WHERE id = ?")?;
+
+        for id in ids.iter() {
+            copy_stmt.execute([id])?;
+
+            let blobs = blobs_stmt
+                .query_map([id], |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        recompress(row.get_ref(1)?),
+                        recompress(row.get_ref(2)?),
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            // logs from before compression are stored as text and kept that way
+            for (rowid, skills, damage_stats) in blobs {
+                let (Some((skills, skills_len)), Some((damage_stats, damage_stats_len))) =
+                    (skills, damage_stats)
+                else {
+                    continue;
+                };
+                let before = skills_len + damage_stats_len;
+                let after = skills.len() + damage_stats.len();
+                blob_bytes += before;
+                // only keep the recompressed blobs when they actually came out smaller
+                if after < before {
+                    recompress_stmt.execute(params![skills, damage_stats, rowid])?;
+                    saved_bytes += before - after;
+                }
+            }
+
+            delete_stmt.execute([id])?;
+            archived_stmt.execute([id])?;
+        }
+
+        ids
+    };
+    tx.commit()?;
+
+    if !ids.is_empty() {
+        info!(
+            "archived {} encounters, recompressing saved {} of {} blob bytes",
+            ids.len(),
+            saved_bytes,
+            blob_bytes
+        );
+    }
+    Ok(ids.len())
+}
+
+// returns the recompressed blob along with the size it had before
+fn recompress(value: ValueRef) -> Option<(Vec<u8>, usize)> {
+    let ValueRef::Blob(bytes) = value else {
+        return None;
+    };
+    let mut json = Vec::new();
+    GzDecoder::new(bytes).read_to_end(&mut json).ok()?;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(&json).ok()?;
+    encoder.finish().ok().map(|blob| (blob, bytes.len()))
+}
+
+// drops archived entities whose encounter was deleted from the main db
+pub fn prune_archive(conn: &Connection) -> Result<usize, rusqlite::Error> {
+    create_archive(conn)?;
+    conn.execute(
+        "DELETE FROM archive.entity WHERE encounter_id NOT IN (SELECT id FROM main.encounter)",
+        [],
+    )
+}
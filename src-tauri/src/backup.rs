@@ -0,0 +1,339 @@
+use crate::archive::{self, ARCHIVE_FILE};
+use crate::migrations;
+use crate::parser::models::*;
+use anyhow::Result;
+use chrono::{NaiveDateTime, Utc};
+use log::{info, warn};
+use rusqlite::backup::Progress;
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const BACKUP_DIR: &str = "backups";
+const TIMESTAMP_FORMAT: &str = "%Y%m%d%H%M%S";
+// for backups taken without settings at hand, like the ones before migrations
+pub const DEFAULT_KEEP: u32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackupKind {
+    Scheduled,
+    Delete,
+    Migration,
+    Restore,
+}
+
+impl BackupKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            BackupKind::Scheduled => "scheduled",
+            BackupKind::Delete => "delete",
+            BackupKind::Migration => "migration",
+            BackupKind::Restore => "restore",
+        }
+    }
+}
+
+// copies the db with the online backup api, safe to run while the meter is saving
+// backups are named encounters.{kind}.{timestamp}.db and rotated per kind, the archive
+// goes next to it as encounters.{kind}.{timestamp}.archive.db so the two restore together
+pub fn backup_database(
+    conn: &Connection,
+    resource_path: &Path,
+    kind: BackupKind,
+    keep: u32,
+) -> Result<PathBuf, rusqlite::Error> {
+    let dir = resource_path.join(BACKUP_DIR);
+    // if this fails the backup below fails to open the file and reports it
+    fs::create_dir_all(&dir).ok();
+
+    let path = dir.join(format!(
+        "encounters.{}.{}.db",
+        kind.as_str(),
+        Utc::now().format(TIMESTAMP_FORMAT)
+    ));
+    conn.backup(DatabaseName::Main, &path, None)?;
+    info!("backed up database to {:?}", path);
+
+    let archive_path = resource_path.join(ARCHIVE_FILE);
+    if archive_path.exists() {
+        let archive_conn =
+            Connection::open_with_flags(&archive_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let archive_backup_path = archive_backup_path(&path);
+        archive_conn.backup(DatabaseName::Main, &archive_backup_path, None)?;
+        info!("backed up archive to {:?}", archive_backup_path);
+    }
+
+    rotate_backups(&dir, kind, keep);
+    Ok(path)
+}
+
+fn rotate_backups(dir: &Path, kind: BackupKind, keep: u32) {
+    let mut backups = match read_backups(dir) {
+        Ok(backups) => backups,
+        Err(e) => {
+            warn!("could not read backups to rotate: {}", e);
+            return;
+        }
+    };
+    backups.retain(|backup| backup.kind == kind.as_str());
+
+    // always keep the one that was just taken
+    for backup in backups.iter().skip(keep.max(1) as usize) {
+        let path = dir.join(&backup.name);
+        match fs::remove_file(&path) {
+            Ok(_) => info!("removed old backup {}", backup.name),
+            Err(e) => warn!("could not remove old backup {}: {}", backup.name, e),
+        }
+        let archive_backup_path = archive_backup_path(&path);
+        if archive_backup_path.exists() {
+            if let Err(e) = fs::remove_file(&archive_backup_path) {
+                warn!(
+                    "could not remove old archive backup {:?}: {}",
+                    archive_backup_path, e
+                );
+            }
+        }
+    }
+}
+
+fn archive_backup_path(backup_path: &Path) -> PathBuf {
+    backup_path.with_extension("archive.db")
+}
+
+// newest first
+fn read_backups(dir: &Path) -> io::Result<Vec<DbBackup>> {
+    let mut backups = vec![];
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some((kind, timestamp)) = name
+            .strip_prefix("encounters.")
+            .and_then(|name| name.strip_suffix(".db"))
+            .and_then(|name| name.rsplit_once('.'))
+        else {
+            continue;
+        };
+        // archive backups end in .archive.db and are skipped here with the bad timestamp
+        let Ok(created) = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT) else {
+            continue;
+        };
+
+        backups.push(DbBackup {
+            kind: kind.to_string(),
+            created: created.and_utc().timestamp_millis(),
+            size: entry.metadata()?.len(),
+            name,
+        });
+    }
+    backups.sort_by(|a, b| b.created.cmp(&a.created));
+
+    Ok(backups)
+}
+
+pub fn list_backups(resource_path: &Path) -> io::Result<Vec<DbBackup>> {
+    let dir = resource_path.join(BACKUP_DIR);
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+    read_backups(&dir)
+}
+
+// takes a scheduled backup when the newest one is older than the interval
+pub fn run_scheduled_backup(resource_path: &Path, settings: &BackupSettings) -> Result<()> {
+    let db_path = resource_path.join("encounters.db");
+    if !settings.enabled || !db_path.exists() {
+        return Ok(());
+    }
+
+    let last_backup = list_backups(resource_path)?
+        .into_iter()
+        .find(|backup| backup.kind == BackupKind::Scheduled.as_str())
+        .map(|backup| backup.created);
+    let interval = settings.interval_hours.max(1) as i64 * 60 * 60 * 1000;
+    if last_backup.is_some_and(|created| Utc::now().timestamp_millis() - created < interval) {
+        return Ok(());
+    }
+
+    let conn = Connection::open_with_flags(&db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    backup_database(&conn, resource_path, BackupKind::Scheduled, settings.keep)?;
+    Ok(())
+}
+
+// replaces the db with a backup, the current db is backed up first so a restore can be undone
+pub fn restore_backup(
+    conn: &mut Connection,
+    resource_path: &Path,
+    name: &str,
+    keep: u32,
+) -> Result<()> {
+    // only names from the backup dir, never an arbitrary path
+    if !list_backups(resource_path)?.iter().any(|backup| backup.name == name) {
+        let message = format!("backup {} does not exist", name);
+        return Err(io::Error::new(io::ErrorKind::NotFound, message).into());
+    }
+    let backup_path = resource_path.join(BACKUP_DIR).join(name);
+
+    backup_database(conn, resource_path, BackupKind::Restore, keep)?;
+    conn.restore(DatabaseName::Main, &backup_path, None::<fn(Progress)>)?;
+    info!("restored database from {}", name);
+
+    // the archive has to match the restored db, otherwise its archived flags point at
+    // entities that are gone or the same encounters get archived a second time
+    archive::create_archive(conn)?;
+    let archive_backup_path = archive_backup_path(&backup_path);
+    if archive_backup_path.exists() {
+        conn.restore(
+            DatabaseName::Attached("archive"),
+            &archive_backup_path,
+            None::<fn(Progress)>,
+        )?;
+        info!("restored archive from {:?}", archive_backup_path);
+    } else {
+        // taken before anything was archived
+        conn.execute("DELETE FROM archive.entity", [])?;
+    }
+
+    // older backups can be behind on schema
+    migrations::run_migrations(conn, resource_path, false)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backup_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("backups-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn backup_names(dir: &Path) -> Vec<String> {
+        read_backups(dir)
+            .unwrap()
+            .into_iter()
+            .map(|backup| backup.name)
+            .collect()
+    }
+
+    #[test]
+    fn rotation_keeps_the_newest_of_a_kind() {
+        let dir = backup_dir("rotate");
+        for day in 1..=4 {
+            let name = format!("encounters.scheduled.2026100{}000000.db", day);
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        fs::write(dir.join("encounters.delete.20261001000000.db"), b"").unwrap();
+
+        rotate_backups(&dir, BackupKind::Scheduled, 2);
+        assert_eq!(
+            backup_names(&dir),
+            vec![
+                "encounters.scheduled.20261004000000.db",
+                "encounters.scheduled.20261003000000.db",
+                "encounters.delete.20261001000000.db",
+            ]
+        );
+
+        // the backup that was just taken survives even with nothing to keep
+        rotate_backups(&dir, BackupKind::Scheduled, 0);
+        assert_eq!(
+            backup_names(&dir),
+            vec![
+                "encounters.scheduled.20261004000000.db",
+                "encounters.delete.20261001000000.db",
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_files_that_are_not_backups() {
+        let dir = backup_dir("unrelated");
+        fs::write(dir.join("encounters.scheduled.20261001000000.db"), b"").unwrap();
+        fs::write(dir.join("encounters.scheduled.latest.db"), b"").unwrap();
+        fs::write(dir.join("notes.txt"), b"").unwrap();
+
+        assert_eq!(
+            backup_names(&dir),
+            vec!["encounters.scheduled.20261001000000.db"]
+        );
+    }
+
+    #[test]
+    fn backs_up_a_copy_of_the_db() {
+        let dir = backup_dir("copy");
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE t (v INTEGER); INSERT INTO t VALUES (1);")
+            .unwrap();
+
+        let path = backup_database(&conn, &dir, BackupKind::Migration, DEFAULT_KEEP).unwrap();
+        let backups = list_backups(&dir).unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(backups[0].kind, "migration");
+
+        let copy = Connection::open(path).unwrap();
+        let value: i64 = copy
+            .query_row("SELECT v FROM t", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(value, 1);
+    }
+
+    fn archived_names(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn.prepare("SELECT name FROM archive.entity").unwrap();
+        stmt.query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn archive_is_backed_up_and_restored_with_the_db() {
+        let dir = backup_dir("archive");
+        let mut conn = Connection::open(dir.join("encounters.db")).unwrap();
+        migrations::run_migrations(&mut conn, &dir, false).unwrap();
+        archive::create_archive(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO archive.entity (name, encounter_id, entity_key) VALUES ('a', 1, 'pc:1')",
+            [],
+        )
+        .unwrap();
+
+        let path = backup_database(&conn, &dir, BackupKind::Scheduled, DEFAULT_KEEP).unwrap();
+        assert!(archive_backup_path(&path).exists());
+        // listed once, the archive copy is not a backup of its own
+        assert_eq!(list_backups(&dir).unwrap().len(), 1);
+
+        conn.execute("DELETE FROM archive.entity", []).unwrap();
+        conn.execute(
+            "INSERT INTO archive.entity (name, encounter_id, entity_key) VALUES ('b', 2, 'pc:2')",
+            [],
+        )
+        .unwrap();
+
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        restore_backup(&mut conn, &dir, &name, DEFAULT_KEEP).unwrap();
+        assert_eq!(archived_names(&conn), vec!["a".to_string()]);
+    }
+
+    #[test]
+    fn rotation_removes_the_archive_copy() {
+        let dir = backup_dir("rotate-archive");
+        let backup = |day: u32, suffix: &str| {
+            dir.join(format!(
+                "encounters.scheduled.2026100{}000000.{}",
+                day, suffix
+            ))
+        };
+        for day in 1..=2 {
+            fs::write(backup(day, "db"), b"").unwrap();
+            fs::write(backup(day, "archive.db"), b"").unwrap();
+        }
+
+        rotate_backups(&dir, BackupKind::Scheduled, 1);
+        assert!(backup(2, "archive.db").exists());
+        assert!(!backup(1, "db").exists());
+        assert!(!backup(1, "archive.db").exists());
+    }
+}
@@ -0,0 +1,127 @@
+use crate::archive;
+use log::{error, info};
+use rusqlite::{ffi, Connection};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+// idle read connections kept around, more are opened when needed
+const MAX_IDLE_READERS: usize = 4;
+
+type WriteJob = Box<dyn FnOnce(&mut Connection) + Send>;
+
+// shared access to encounters.db, reads go through a small pool of connections and
+// every write is queued to a single writer thread so saves and deletes never race
+pub struct Database {
+    path: PathBuf,
+    readers: Mutex<Vec<Connection>>,
+    writer: Sender<WriteJob>,
+}
+
+impl Database {
+    pub fn open(resource_path: &Path) -> Result<Self, rusqlite::Error> {
+        let path = resource_path.join("encounters.db");
+        let mut conn = open_connection(&path)?;
+        // persistent, readers no longer block the writer and the other way around
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| {
+            row.get::<_, String>(0)
+        })?;
+        // readers attach the archive read only, so it has to exist before the first one opens
+        archive::create_archive(&conn)?;
+
+        let (writer, jobs) = mpsc::channel::<WriteJob>();
+        thread::Builder::new()
+            .name("db-writer".to_string())
+            .spawn(move || {
+                for job in jobs {
+                    job(&mut conn);
+                }
+                info!("db writer stopped");
+            })
+            .map_err(|e| {
+                error!("could not start db writer: {}", e);
+                writer_stopped()
+            })?;
+
+        Ok(Self {
+            path,
+            readers: Mutex::new(Vec::new()),
+            writer,
+        })
+    }
+
+    pub fn reader(&self) -> Result<PooledConnection<'_>, rusqlite::Error> {
+        let idle = self.readers.lock().unwrap().pop();
+        let conn = match idle {
+            Some(conn) => conn,
+            None => {
+                let conn = open_connection(&self.path)?;
+                // archived entities and all_entities are read through every connection
+                archive::attach_archive(&conn)?;
+                conn
+            }
+        };
+        Ok(PooledConnection {
+            conn: Some(conn),
+            pool: &self.readers,
+        })
+    }
+
+    // runs on the writer thread and waits for the result
+    pub fn write<T, F>(&self, f: F) -> Result<T, rusqlite::Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T, rusqlite::Error> + Send + 'static,
+    {
+        let (result_tx, result_rx) = mpsc::channel();
+        let job: WriteJob = Box::new(move |conn| {
+            result_tx.send(f(conn)).ok();
+        });
+        self.writer.send(job).map_err(|_| writer_stopped())?;
+        result_rx.recv().map_err(|_| writer_stopped())?
+    }
+}
+
+fn open_connection(path: &Path) -> Result<Connection, rusqlite::Error> {
+    let conn = Connection::open(path)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    Ok(conn)
+}
+
+fn writer_stopped() -> rusqlite::Error {
+    rusqlite::Error::SqliteFailure(
+        ffi::Error::new(ffi::SQLITE_MISUSE),
+        Some("db writer is not running".to_string()),
+    )
+}
+
+// goes back to the pool when dropped
+pub struct PooledConnection<'a> {
+    conn: Option<Connection>,
+    pool: &'a Mutex<Vec<Connection>>,
+}
+
+impl Deref for PooledConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().unwrap()
+    }
+}
+
+impl Drop for PooledConnection<'_> {
+    fn drop(&mut self) {
+        let Some(conn) = self.conn.take() else {
+            return;
+        };
+        if let Ok(mut pool) = self.pool.lock() {
+            if pool.len() < MAX_IDLE_READERS {
+                pool.push(conn);
+            }
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use log::error;
+use rusqlite::ErrorCode;
+use serde::Serialize;
+use std::fmt::{self, Display};
+use std::io;
+
+// returned by commands instead of panicking, serialized as { kind, message } for the ui
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum AppError {
+    NotFound(String),
+    Corrupt(String),
+    Locked(String),
+    Io(String),
+}
+
+impl Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::NotFound(message) => write!(f, "not found: {}", message),
+            AppError::Corrupt(message) => write!(f, "corrupt data: {}", message),
+            AppError::Locked(message) => write!(f, "database locked: {}", message),
+            AppError::Io(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        let message = e.to_string();
+        match e {
+            rusqlite::Error::QueryReturnedNoRows => AppError::NotFound(message),
+            rusqlite::Error::SqliteFailure(failure, _) => match failure.code {
+                ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked => AppError::Locked(message),
+                ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase => AppError::Corrupt(message),
+                _ => AppError::Io(message),
+            },
+            rusqlite::Error::FromSqlConversionFailure(..)
+            | rusqlite::Error::InvalidColumnType(..)
+            | rusqlite::Error::IntegralValueOutOfRange(..)
+            | rusqlite::Error::Utf8Error(_) => AppError::Corrupt(message),
+            _ => AppError::Io(message),
+        }
+    }
+}
+
+impl From<io::Error> for AppError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::NotFound => AppError::NotFound(e.to_string()),
+            _ => AppError::Io(e.to_string()),
+        }
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::Corrupt(e.to_string())
+    }
+}
+
+impl From<anyhow::Error> for AppError {
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast::<rusqlite::Error>() {
+            Ok(e) => e.into(),
+            Err(e) => match e.downcast::<io::Error>() {
+                Ok(e) => e.into(),
+                Err(e) => AppError::Io(e.to_string()),
+            },
+        }
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for AppError {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        AppError::Io(e.to_string())
+    }
+}
+
+pub trait LogContext<T> {
+    // logs the error with what the command was doing before it goes back to the ui
+    fn log_context<C: Display>(self, context: impl FnOnce() -> C) -> Result<T, AppError>;
+}
+
+impl<T, E: Into<AppError>> LogContext<T> for Result<T, E> {
+    fn log_context<C: Display>(self, context: impl FnOnce() -> C) -> Result<T, AppError> {
+        self.map_err(|e| {
+            let e = e.into();
+            error!("{}: {}", context(), e);
+            e
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::ffi;
+    use rusqlite::types::Type;
+
+    fn sqlite_failure(code: i32) -> rusqlite::Error {
+        rusqlite::Error::SqliteFailure(ffi::Error::new(code), None)
+    }
+
+    #[test]
+    fn maps_sqlite_errors() {
+        assert!(matches!(
+            AppError::from(rusqlite::Error::QueryReturnedNoRows),
+            AppError::NotFound(_)
+        ));
+        assert!(matches!(
+            AppError::from(sqlite_failure(ffi::SQLITE_BUSY)),
+            AppError::Locked(_)
+        ));
+        assert!(matches!(
+            AppError::from(sqlite_failure(ffi::SQLITE_CORRUPT)),
+            AppError::Corrupt(_)
+        ));
+        assert!(matches!(
+            AppError::from(sqlite_failure(ffi::SQLITE_FULL)),
+            AppError::Io(_)
+        ));
+    }
+
+    #[test]
+    fn maps_bad_column_data_to_corrupt() {
+        let json = serde_json::from_str::<u32>("{").unwrap_err();
+        let conversion = rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(json));
+        assert!(matches!(AppError::from(conversion), AppError::Corrupt(_)));
+
+        let json = serde_json::from_str::<u32>("{").unwrap_err();
+        assert!(matches!(AppError::from(json), AppError::Corrupt(_)));
+    }
+
+    #[test]
+    fn maps_io_errors() {
+        let missing = io::Error::new(io::ErrorKind::NotFound, "missing");
+        assert!(matches!(AppError::from(missing), AppError::NotFound(_)));
+        let denied = io::Error::new(io::ErrorKind::PermissionDenied, "denied");
+        assert!(matches!(AppError::from(denied), AppError::Io(_)));
+    }
+
+    #[test]
+    fn unwraps_anyhow_errors() {
+        let locked = anyhow::Error::from(sqlite_failure(ffi::SQLITE_LOCKED));
+        assert!(matches!(AppError::from(locked), AppError::Locked(_)));
+        let missing = anyhow::Error::from(io::Error::new(io::ErrorKind::NotFound, "missing"));
+        assert!(matches!(AppError::from(missing), AppError::NotFound(_)));
+        assert!(matches!(
+            AppError::from(anyhow::anyhow!("other")),
+            AppError::Io(_)
+        ));
+    }
+
+    #[test]
+    fn serializes_kind_and_message() {
+        let json = serde_json::to_value(AppError::NotFound("encounter 1".to_string())).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "kind": "notFound", "message": "encounter 1" })
+        );
+    }
+}
@@ -0,0 +1,34 @@
+use crate::export::{Cell, Table};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+pub fn write_csv(path: &Path, table: &Table) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    let header: Vec<String> = table.columns.iter().map(|c| escape(c)).collect();
+    writeln!(writer, "{}", header.join(","))?;
+    for row in table.rows.iter() {
+        let line: Vec<String> = row.iter().map(format_cell).collect();
+        writeln!(writer, "{}", line.join(","))?;
+    }
+
+    writer.flush()
+}
+
+fn format_cell(cell: &Cell) -> String {
+    match cell {
+        Cell::Empty => String::new(),
+        Cell::Int(value) => value.to_string(),
+        Cell::Float(value) => format!("{:.2}", value),
+        Cell::Text(value) => escape(value),
+    }
+}
+
+fn escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,232 @@
+use crate::parser::models::*;
+use parquet::basic::Compression;
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DataType, Int32Type, Int64Type};
+use parquet::errors::{ParquetError, Result};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::{SerializedFileWriter, SerializedRowGroupWriter};
+use parquet::format::KeyValue;
+use parquet::schema::parser::parse_message_type;
+use std::fs::File;
+use std::sync::Arc;
+
+// bump when the schema changes, stored in the file metadata
+pub const HIT_SCHEMA_VERSION: u32 = 1;
+
+// columns are written in this order, keep write_row_group in sync
+const HIT_SCHEMA: &str = "
+message skill_hit {
+    required int64 encounter_id;
+    required int64 fight_start;
+    required binary boss (UTF8);
+    optional binary difficulty (UTF8);
+    required boolean cleared;
+    required binary player (UTF8);
+    required int32 class_id;
+    required int32 skill_id;
+    required binary skill_name (UTF8);
+    required int64 cast_timestamp;
+    required int64 timestamp;
+    required int64 damage;
+    required boolean crit;
+    required boolean back_attack;
+    required boolean front_attack;
+    required boolean flank_attack;
+    required group buffed_by (LIST) {
+        repeated group list {
+            required int32 element;
+        }
+    }
+    required group debuffed_by (LIST) {
+        repeated group list {
+            required int32 element;
+        }
+    }
+    required int64 rdps_damage_received;
+    required int64 rdps_damage_received_support;
+}
+";
+
+#[derive(Default)]
+struct ListColumn {
+    values: Vec<i32>,
+    def_levels: Vec<i16>,
+    rep_levels: Vec<i16>,
+}
+
+impl ListColumn {
+    // an empty list is a single entry with no value, elements after the first repeat
+    fn push(&mut self, values: &[u32]) {
+        if values.is_empty() {
+            self.def_levels.push(0);
+            self.rep_levels.push(0);
+            return;
+        }
+        for (index, value) in values.iter().enumerate() {
+            self.values.push(*value as i32);
+            self.def_levels.push(1);
+            self.rep_levels.push(if index == 0 { 0 } else { 1 });
+        }
+    }
+}
+
+#[derive(Default)]
+struct HitColumns {
+    rows: usize,
+    encounter_id: Vec<i64>,
+    fight_start: Vec<i64>,
+    boss: Vec<ByteArray>,
+    difficulty: Vec<ByteArray>,
+    difficulty_def_levels: Vec<i16>,
+    cleared: Vec<bool>,
+    player: Vec<ByteArray>,
+    class_id: Vec<i32>,
+    skill_id: Vec<i32>,
+    skill_name: Vec<ByteArray>,
+    cast_timestamp: Vec<i64>,
+    timestamp: Vec<i64>,
+    damage: Vec<i64>,
+    crit: Vec<bool>,
+    back_attack: Vec<bool>,
+    front_attack: Vec<bool>,
+    flank_attack: Vec<bool>,
+    buffed_by: ListColumn,
+    debuffed_by: ListColumn,
+    rdps_damage_received: Vec<i64>,
+    rdps_damage_received_support: Vec<i64>,
+}
+
+// one row per skill hit, each encounter goes into its own row group
+pub struct HitWriter {
+    writer: SerializedFileWriter<File>,
+    rows: usize,
+}
+
+impl HitWriter {
+    pub fn new(file: File) -> Result<Self> {
+        let schema = Arc::new(parse_message_type(HIT_SCHEMA)?);
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .set_key_value_metadata(Some(vec![KeyValue::new(
+                "loa_logs.hit_schema_version".to_string(),
+                HIT_SCHEMA_VERSION.to_string(),
+            )]))
+            .build();
+
+        Ok(Self {
+            writer: SerializedFileWriter::new(file, schema, Arc::new(props))?,
+            rows: 0,
+        })
+    }
+
+    pub fn add_encounter(&mut self, encounter_id: &str, encounter: &Encounter) -> Result<()> {
+        let encounter_id = encounter_id.parse::<i64>().unwrap_or_default();
+        let mut columns = HitColumns::default();
+
+        for player in encounter
+            .entities
+            .values()
+            .filter(|e| e.entity_type == EntityType::PLAYER && e.class_id > 0)
+        {
+            for skill in player.skills.values() {
+                for cast in skill.skill_cast_log.iter() {
+                    for hit in cast.hits.iter() {
+                        columns.rows += 1;
+                        columns.encounter_id.push(encounter_id);
+                        columns.fight_start.push(encounter.fight_start);
+                        columns.boss.push(encounter.current_boss_name.as_str().into());
+                        match &encounter.difficulty {
+                            Some(difficulty) => {
+                                columns.difficulty.push(difficulty.as_str().into());
+                                columns.difficulty_def_levels.push(1);
+                            }
+                            None => columns.difficulty_def_levels.push(0),
+                        }
+                        columns.cleared.push(encounter.cleared);
+                        columns.player.push(player.name.as_str().into());
+                        columns.class_id.push(player.class_id as i32);
+                        columns.skill_id.push(skill.id as i32);
+                        columns.skill_name.push(skill.name.as_str().into());
+                        columns.cast_timestamp.push(cast.timestamp);
+                        columns.timestamp.push(hit.timestamp);
+                        columns.damage.push(hit.damage);
+                        columns.crit.push(hit.crit);
+                        columns.back_attack.push(hit.back_attack);
+                        columns.front_attack.push(hit.front_attack);
+                        columns.flank_attack.push(hit.flank_attack);
+                        columns.buffed_by.push(&hit.buffed_by);
+                        columns.debuffed_by.push(&hit.debuffed_by);
+                        columns.rdps_damage_received.push(hit.rdps_damage_received);
+                        columns
+                            .rdps_damage_received_support
+                            .push(hit.rdps_damage_received_support);
+                    }
+                }
+            }
+        }
+
+        if columns.rows == 0 {
+            return Ok(());
+        }
+        self.rows += columns.rows;
+        self.write_row_group(&columns)
+    }
+
+    fn write_row_group(&mut self, c: &HitColumns) -> Result<()> {
+        let mut row_group = self.writer.next_row_group()?;
+        write_column::<Int64Type>(&mut row_group, &c.encounter_id, None, None)?;
+        write_column::<Int64Type>(&mut row_group, &c.fight_start, None, None)?;
+        write_column::<ByteArrayType>(&mut row_group, &c.boss, None, None)?;
+        write_column::<ByteArrayType>(
+            &mut row_group,
+            &c.difficulty,
+            Some(c.difficulty_def_levels.as_slice()),
+            None,
+        )?;
+        write_column::<BoolType>(&mut row_group, &c.cleared, None, None)?;
+        write_column::<ByteArrayType>(&mut row_group, &c.player, None, None)?;
+        write_column::<Int32Type>(&mut row_group, &c.class_id, None, None)?;
+        write_column::<Int32Type>(&mut row_group, &c.skill_id, None, None)?;
+        write_column::<ByteArrayType>(&mut row_group, &c.skill_name, None, None)?;
+        write_column::<Int64Type>(&mut row_group, &c.cast_timestamp, None, None)?;
+        write_column::<Int64Type>(&mut row_group, &c.timestamp, None, None)?;
+        write_column::<Int64Type>(&mut row_group, &c.damage, None, None)?;
+        write_column::<BoolType>(&mut row_group, &c.crit, None, None)?;
+        write_column::<BoolType>(&mut row_group, &c.back_attack, None, None)?;
+        write_column::<BoolType>(&mut row_group, &c.front_attack, None, None)?;
+        write_column::<BoolType>(&mut row_group, &c.flank_attack, None, None)?;
+        for list in [&c.buffed_by, &c.debuffed_by] {
+            write_column::<Int32Type>(
+                &mut row_group,
+                &list.values,
+                Some(list.def_levels.as_slice()),
+                Some(list.rep_levels.as_slice()),
+            )?;
+        }
+        write_column::<Int64Type>(&mut row_group, &c.rdps_damage_received, None, None)?;
+        write_column::<Int64Type>(&mut row_group, &c.rdps_damage_received_support, None, None)?;
+        row_group.close()?;
+
+        Ok(())
+    }
+
+    // returns the number of rows written
+    pub fn finish(self) -> Result<usize> {
+        self.writer.close()?;
+        Ok(self.rows)
+    }
+}
+
+fn write_column<T: DataType>(
+    row_group: &mut SerializedRowGroupWriter<'_, File>,
+    values: &[T::T],
+    def_levels: Option<&[i16]>,
+    rep_levels: Option<&[i16]>,
+) -> Result<()> {
+    let mut column = row_group
+        .next_column()?
+        .ok_or_else(|| ParquetError::General("hit schema has fewer columns".to_string()))?;
+    column
+        .typed::<T>()
+        .write_batch(values, def_levels, rep_levels)?;
+    column.close()
+}
@@ -0,0 +1,118 @@
+mod csv;
+mod hits;
+mod tables;
+mod xlsx;
+
+pub use hits::HitWriter;
+pub use tables::EncounterTables;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub enum Cell {
+    Empty,
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl From<i64> for Cell {
+    fn from(value: i64) -> Self {
+        Cell::Int(value)
+    }
+}
+
+impl From<u32> for Cell {
+    fn from(value: u32) -> Self {
+        Cell::Int(value as i64)
+    }
+}
+
+impl From<f64> for Cell {
+    fn from(value: f64) -> Self {
+        Cell::Float(value)
+    }
+}
+
+impl From<bool> for Cell {
+    fn from(value: bool) -> Self {
+        Cell::Int(value as i64)
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(value: &str) -> Self {
+        Cell::Text(value.to_string())
+    }
+}
+
+impl From<Option<String>> for Cell {
+    fn from(value: Option<String>) -> Self {
+        value.map_or(Cell::Empty, Cell::Text)
+    }
+}
+
+pub struct Table {
+    pub name: &'static str,
+    pub columns: Vec<&'static str>,
+    // columns identifying a row, never dropped by select
+    keys: &'static [&'static str],
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+    pub fn new(
+        name: &'static str,
+        columns: &[&'static str],
+        keys: &'static [&'static str],
+    ) -> Self {
+        Self {
+            name,
+            columns: columns.to_vec(),
+            keys,
+            rows: Vec::new(),
+        }
+    }
+
+    // keeps the selected columns and the keys in table order, an empty selection keeps
+    // everything
+    pub fn select(&mut self, selection: &[String]) {
+        if selection.is_empty() {
+            return;
+        }
+
+        let keep: Vec<bool> = self
+            .columns
+            .iter()
+            .map(|column| self.keys.contains(column) || selection.iter().any(|s| s == column))
+            .collect();
+        self.columns = self
+            .columns
+            .iter()
+            .zip(keep.iter())
+            .filter(|(_, keep)| **keep)
+            .map(|(column, _)| *column)
+            .collect();
+        for row in self.rows.iter_mut() {
+            let mut index = 0;
+            row.retain(|_| {
+                index += 1;
+                keep[index - 1]
+            });
+        }
+    }
+}
+
+// one csv per table, plus a workbook with a sheet per table
+pub fn write_tables(dir: &Path, tables: &[Table], xlsx: bool) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for table in tables.iter() {
+        csv::write_csv(&dir.join(format!("{}.csv", table.name)), table)?;
+    }
+    if xlsx {
+        xlsx::write_xlsx(&dir.join("encounters.xlsx"), tables)?;
+    }
+
+    Ok(())
+}
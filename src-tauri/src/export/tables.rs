@@ -0,0 +1,224 @@
+use crate::analysis::percent;
+use crate::export::{Cell, Table};
+use crate::parser::models::*;
+use hashbrown::HashMap;
+
+const PLAYER_COLUMNS: &[&str] = &[
+    "encounter_id",
+    "fight_start",
+    "boss",
+    "difficulty",
+    "cleared",
+    "duration",
+    "name",
+    "class",
+    "class_id",
+    "gear_score",
+    "spec",
+    "damage",
+    "dps",
+    "damage_percent",
+    "crit_rate",
+    "crit_damage_percent",
+    "back_attack_percent",
+    "front_attack_percent",
+    "buffed_by_support_percent",
+    "buffed_by_identity_percent",
+    "buffed_by_hat_percent",
+    "debuffed_by_support_percent",
+    "rdps_damage_received",
+    "rdps_damage_received_support",
+    "rdps_damage_given",
+    "rdps_damage",
+    "rdps",
+    "deaths",
+    "damage_taken",
+    "counters",
+];
+
+const SKILL_COLUMNS: &[&str] = &[
+    "encounter_id",
+    "name",
+    "class",
+    "skill_id",
+    "skill_name",
+    "damage",
+    "dps",
+    "damage_percent",
+    "max_damage",
+    "casts",
+    "hits",
+    "crit_rate",
+    "crit_damage_percent",
+    "back_attack_percent",
+    "front_attack_percent",
+    "buffed_by_support_percent",
+    "buffed_by_identity_percent",
+    "buffed_by_hat_percent",
+    "debuffed_by_support_percent",
+    "rdps_damage_received",
+    "rdps_damage_received_support",
+    "rdps_damage_given",
+];
+
+const BUFF_COLUMNS: &[&str] = &[
+    "encounter_id",
+    "name",
+    "class",
+    "buff_id",
+    "buff_name",
+    "buff_category",
+    "debuff",
+    "damage",
+    "percent",
+];
+
+// join columns between the tables, kept whatever columns are selected
+const PLAYER_KEYS: &[&str] = &["encounter_id", "name"];
+const SKILL_KEYS: &[&str] = &["encounter_id", "name", "skill_id"];
+const BUFF_KEYS: &[&str] = &["encounter_id", "name", "buff_id"];
+
+pub struct EncounterTables {
+    players: Table,
+    skills: Table,
+    buffs: Table,
+}
+
+impl Default for EncounterTables {
+    fn default() -> Self {
+        Self {
+            players: Table::new("players", PLAYER_COLUMNS, PLAYER_KEYS),
+            skills: Table::new("skills", SKILL_COLUMNS, SKILL_KEYS),
+            buffs: Table::new("buffs", BUFF_COLUMNS, BUFF_KEYS),
+        }
+    }
+}
+
+impl EncounterTables {
+    pub fn add_encounter(&mut self, encounter_id: &str, encounter: &Encounter) {
+        let encounter_id = encounter_id.parse::<i64>().unwrap_or_default();
+        let seconds = (encounter.duration / 1000).max(1);
+        let total_damage = encounter.encounter_damage_stats.total_damage_dealt;
+
+        let mut players: Vec<&EncounterEntity> = encounter
+            .entities
+            .values()
+            .filter(|e| e.entity_type == EntityType::PLAYER && e.class_id > 0)
+            .collect();
+        players.sort_by(|a, b| b.damage_stats.damage_dealt.cmp(&a.damage_stats.damage_dealt));
+
+        for player in players {
+            let stats = &player.damage_stats;
+            let damage = stats.damage_dealt;
+            let rdps_damage = damage - stats.rdps_damage_received + stats.rdps_damage_given;
+            self.players.rows.push(vec![
+                encounter_id.into(),
+                encounter.fight_start.into(),
+                encounter.current_boss_name.as_str().into(),
+                encounter.difficulty.clone().into(),
+                encounter.cleared.into(),
+                encounter.duration.into(),
+                player.name.as_str().into(),
+                player.class.as_str().into(),
+                player.class_id.into(),
+                (player.gear_score as f64).into(),
+                player.spec.clone().into(),
+                damage.into(),
+                (damage / seconds).into(),
+                percent(damage, total_damage).into(),
+                percent(player.skill_stats.crits, player.skill_stats.hits).into(),
+                percent(stats.crit_damage, damage).into(),
+                percent(stats.back_attack_damage, damage).into(),
+                percent(stats.front_attack_damage, damage).into(),
+                percent(stats.buffed_by_support, damage).into(),
+                percent(stats.buffed_by_identity, damage).into(),
+                percent(stats.buffed_by_hat, damage).into(),
+                percent(stats.debuffed_by_support, damage).into(),
+                stats.rdps_damage_received.into(),
+                stats.rdps_damage_received_support.into(),
+                stats.rdps_damage_given.into(),
+                rdps_damage.into(),
+                (rdps_damage / seconds).into(),
+                stats.deaths.into(),
+                stats.damage_taken.into(),
+                player.skill_stats.counters.into(),
+            ]);
+
+            let mut skills: Vec<&Skill> = player.skills.values().collect();
+            skills.sort_by(|a, b| b.total_damage.cmp(&a.total_damage));
+            for skill in skills {
+                self.skills.rows.push(vec![
+                    encounter_id.into(),
+                    player.name.as_str().into(),
+                    player.class.as_str().into(),
+                    skill.id.into(),
+                    skill.name.as_str().into(),
+                    skill.total_damage.into(),
+                    (skill.total_damage / seconds).into(),
+                    percent(skill.total_damage, damage).into(),
+                    skill.max_damage.into(),
+                    skill.casts.into(),
+                    skill.hits.into(),
+                    percent(skill.crits, skill.hits).into(),
+                    percent(skill.crit_damage, skill.total_damage).into(),
+                    percent(skill.back_attack_damage, skill.total_damage).into(),
+                    percent(skill.front_attack_damage, skill.total_damage).into(),
+                    percent(skill.buffed_by_support, skill.total_damage).into(),
+                    percent(skill.buffed_by_identity, skill.total_damage).into(),
+                    percent(skill.buffed_by_hat, skill.total_damage).into(),
+                    percent(skill.debuffed_by_support, skill.total_damage).into(),
+                    skill.rdps_damage_received.into(),
+                    skill.rdps_damage_received_support.into(),
+                    skill.rdps_damage_given.into(),
+                ]);
+            }
+
+            let encounter_stats = &encounter.encounter_damage_stats;
+            self.add_buffs(encounter_id, player, &stats.buffed_by, &encounter_stats.buffs, false);
+            self.add_buffs(
+                encounter_id,
+                player,
+                &stats.debuffed_by,
+                &encounter_stats.debuffs,
+                true,
+            );
+        }
+    }
+
+    fn add_buffs(
+        &mut self,
+        encounter_id: i64,
+        player: &EncounterEntity,
+        damage_by_buff: &HashMap<u32, i64>,
+        status_effects: &HashMap<u32, StatusEffect>,
+        debuff: bool,
+    ) {
+        let mut buffs: Vec<(&u32, &i64)> = damage_by_buff.iter().collect();
+        buffs.sort_by(|a, b| b.1.cmp(a.1));
+        for (buff_id, buff_damage) in buffs {
+            let status_effect = status_effects.get(buff_id);
+            self.buffs.rows.push(vec![
+                encounter_id.into(),
+                player.name.as_str().into(),
+                player.class.as_str().into(),
+                (*buff_id).into(),
+                status_effect.map(|s| s.source.name.clone()).into(),
+                status_effect.map(|s| s.buff_category.clone()).into(),
+                debuff.into(),
+                (*buff_damage).into(),
+                percent(*buff_damage, player.damage_stats.damage_dealt).into(),
+            ]);
+        }
+    }
+
+    // columns are selected per table name, a table without a selection keeps every column
+    pub fn into_tables(self, columns: &HashMap<String, Vec<String>>) -> Vec<Table> {
+        let mut tables = vec![self.players, self.skills, self.buffs];
+        for table in tables.iter_mut() {
+            if let Some(selection) = columns.get(table.name) {
+                table.select(selection);
+            }
+        }
+        tables
+    }
+}
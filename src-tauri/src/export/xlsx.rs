@@ -0,0 +1,55 @@
+use crate::export::{Cell, Table};
+use rust_xlsxwriter::{Workbook, XlsxError};
+use std::io;
+use std::path::Path;
+
+// one sheet per table, header row first
+pub fn write_xlsx(path: &Path, tables: &[Table]) -> io::Result<()> {
+    write_workbook(path, tables).map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+}
+
+fn write_workbook(path: &Path, tables: &[Table]) -> Result<(), XlsxError> {
+    let mut workbook = Workbook::new();
+    // hit exports can pass the 4gb zip limit
+    workbook.use_zip_large_file(true);
+
+    for table in tables.iter() {
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(table.name)?;
+
+        for (col, column) in table.columns.iter().enumerate() {
+            worksheet.write_string(0, col_num(col), *column)?;
+        }
+        for (index, row) in table.rows.iter().enumerate() {
+            let sheet_row = row_num(index + 1);
+            for (col, cell) in row.iter().enumerate() {
+                let col = col_num(col);
+                match cell {
+                    Cell::Empty => {}
+                    // excel stores every number as a double
+                    Cell::Int(value) => {
+                        worksheet.write_number(sheet_row, col, *value as f64)?;
+                    }
+                    Cell::Float(value) if value.is_finite() => {
+                        worksheet.write_number(sheet_row, col, *value)?;
+                    }
+                    Cell::Float(_) => {}
+                    Cell::Text(value) => {
+                        worksheet.write_string(sheet_row, col, value)?;
+                    }
+                }
+            }
+        }
+    }
+
+    workbook.save(path)
+}
+
+// past the sheet limits the write fails instead of wrapping around
+fn row_num(index: usize) -> u32 {
+    u32::try_from(index).unwrap_or(u32::MAX)
+}
+
+fn col_num(index: usize) -> u16 {
+    u16::try_from(index).unwrap_or(u16::MAX)
+}
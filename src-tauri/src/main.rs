@@ -3,8 +3,17 @@
     windows_subsystem = "windows"
 )]
 
+mod analysis;
 mod app;
+mod archive;
+mod backup;
+mod db;
+mod error;
+mod export;
+mod migrations;
 mod parser;
+mod retention;
+mod session_assign;
 
 use std::{
     fs::{self, File},
@@ -14,16 +23,25 @@ use std::{
 };
 
 use anyhow::Result;
+use chrono::Utc;
+use db::Database;
+use error::{AppError, LogContext};
 use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use hashbrown::HashMap;
 use log::{error, info, warn};
 use parser::models::*;
 
-use rusqlite::{params, params_from_iter, Connection, Transaction};
+use rusqlite::types::Type;
+use rusqlite::{params, params_from_iter, Connection};
+use serde::de::DeserializeOwned;
 use sysinfo::System;
 use tauri::{
-    api::process::Command, CustomMenuItem, LogicalPosition, LogicalSize, Manager, Position, Size,
-    SystemTray, SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem,
+    api::dialog::{MessageDialogBuilder, MessageDialogKind},
+    api::process::Command,
+    CustomMenuItem, LogicalPosition, LogicalSize, Manager, Position, Size, SystemTray,
+    SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem,
 };
 use tauri_plugin_window_state::{AppHandleExt, StateFlags, WindowExt};
 use tokio::task;
@@ -31,6 +49,9 @@ use window_vibrancy::{apply_blur, clear_blur};
 
 const METER_WINDOW_LABEL: &str = "main";
 const LOGS_WINDOW_LABEL: &str = "logs";
+// how often the backup schedule is checked, the interval itself is in settings
+const BACKUP_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+const DRY_RUN_MIGRATIONS_ARG: &str = "--dry-run-migrations";
 const WINDOW_STATE_FLAGS: StateFlags = StateFlags::from_bits_truncate(
     StateFlags::FULLSCREEN.bits()
         | StateFlags::MAXIMIZED.bits()
@@ -56,8 +77,10 @@ async fn main() -> Result<()> {
     let load_saved_pos = CustomMenuItem::new("load".to_string(), "Load Saved");
     let save_current_pos = CustomMenuItem::new("save".to_string(), "Save Position");
     let reset = CustomMenuItem::new("reset".to_string(), "Reset Window");
+    let weekly_clears = CustomMenuItem::new("weekly-clears".to_string(), "Weekly Clears");
     let tray_menu = SystemTrayMenu::new()
         .add_item(show_logs)
+        .add_item(weekly_clears)
         .add_native_item(SystemTrayMenuItem::Separator)
         .add_item(show_meter)
         .add_item(hide_meter)
@@ -79,12 +102,44 @@ async fn main() -> Result<()> {
                 .resource_dir()
                 .expect("could not get resource dir");
 
+            // checks that pending migrations go through on this db without applying them,
+            // useful before upgrading an old install or after copying in someone's db
+            if std::env::args().any(|arg| arg == DRY_RUN_MIGRATIONS_ARG) {
+                match dry_run_migrations(&resource_path) {
+                    Ok(pending) => info!("migration dry run passed, pending: {:?}", pending),
+                    Err(e) => error!("migration dry run failed: {}", e),
+                }
+                app.handle().exit(0);
+                return Ok(());
+            }
+
             match setup_db(&resource_path) {
                 Ok(_) => (),
                 Err(e) => {
                     warn!("error setting up database: {}", e);
                 }
             }
+            // nothing works without the db, tell the user why instead of vanishing
+            let database =
+                match Database::open(&resource_path).log_context(|| "could not open database") {
+                    Ok(database) => database,
+                    Err(e) => {
+                        let handle = app.handle();
+                        MessageDialogBuilder::new("Could not open database", e.to_string())
+                            .kind(MessageDialogKind::Error)
+                            .show(move |_| handle.exit(1));
+                        return Ok(());
+                    }
+                };
+            apply_retention_rules(&database, &resource_path);
+            purge_expired_trash(&database, &resource_path);
+            app.manage(database);
+
+            let archive_handle = app.handle();
+            let archive_path = resource_path.clone();
+            std::thread::spawn(move || {
+                archive_old_encounters(&archive_handle.state::<Database>(), &archive_path)
+            });
 
             let handle = app.handle();
             tauri::async_runtime::spawn(async move {
@@ -157,11 +212,26 @@ async fn main() -> Result<()> {
                     info!("auto launch game enabled");
                     start_loa_process();
                 }
+
+                if settings.general.weekly_tray_summary {
+                    update_tray_summary(&app.handle());
+                }
             } else {
                 meter_window.show().unwrap();
                 logs_window.show().unwrap();
             }
 
+            let backup_path = resource_path.clone();
+            std::thread::spawn(move || loop {
+                let backup_settings = read_settings(&backup_path)
+                    .map(|settings| settings.backup)
+                    .unwrap_or_default();
+                if let Err(e) = backup::run_scheduled_backup(&backup_path, &backup_settings) {
+                    warn!("scheduled backup failed: {}", e);
+                }
+                std::thread::sleep(BACKUP_CHECK_INTERVAL);
+            });
+
             info!("listening on port: {}", port);
             remove_driver();
             task::spawn_blocking(move || {
@@ -289,6 +359,9 @@ async fn main() -> Result<()> {
                         logs.unminimize().unwrap();
                     }
                 }
+                "weekly-clears" => {
+                    update_tray_summary(app);
+                }
                 _ => {}
             },
             _ => {}
@@ -296,10 +369,26 @@ async fn main() -> Result<()> {
         .invoke_handler(tauri::generate_handler![
             load_encounters_preview,
             load_encounter,
+            compare_encounters,
+            export_encounter,
+            import_encounter,
+            export_encounters,
+            export_skill_hits,
+            load_personal_bests,
+            get_encounter_rankings,
+            get_character_trends,
+            get_roster,
+            get_weekly_clears,
+            get_progression,
+            load_raid_sessions,
+            load_raid_session,
             get_encounter_count,
             open_most_recent_encounter,
             delete_encounter,
             delete_encounters,
+            restore_encounters,
+            empty_trash,
+            archive_encounters,
             toggle_meter_window,
             toggle_logs_window,
             open_url,
@@ -309,6 +398,8 @@ async fn main() -> Result<()> {
             open_db_path,
             delete_encounters_below_min_duration,
             get_db_info,
+            list_backups,
+            restore_backup,
             disable_blur,
             enable_blur,
             write_log,
@@ -334,294 +425,138 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-fn get_db_connection(resource_path: &Path) -> Result<Connection, rusqlite::Error> {
-    let path = resource_path.join("encounters.db");
-    if !path.exists() {
-        setup_db(resource_path)?;
-    }
-    Connection::open(path)
-}
-
 fn setup_db(resource_path: &Path) -> Result<(), rusqlite::Error> {
     info!("setting up database");
     let mut conn = Connection::open(resource_path.join("encounters.db"))?;
-    let tx = conn.transaction()?;
+    migrations::run_migrations(&mut conn, resource_path, false)?;
+    info!("finished setting up database");
+    Ok(())
+}
 
-    // FIXME: replace me with idempotent migrations
+fn dry_run_migrations(resource_path: &Path) -> Result<Vec<&'static str>, rusqlite::Error> {
+    let mut conn = Connection::open(resource_path.join("encounters.db"))?;
+    migrations::run_migrations(&mut conn, resource_path, true)
+}
 
-    let mut stmt = tx.prepare("SELECT 1 FROM sqlite_master WHERE type=? AND name=?")?;
-    if !stmt.exists(["table", "encounter"])? {
-        info!("creating tables");
-        migration_legacy_encounter(&tx)?;
-        migration_legacy_entity(&tx)?;
-    }
+fn get_resource_path(window: &tauri::Window) -> Result<PathBuf, AppError> {
+    window
+        .app_handle()
+        .path_resolver()
+        .resource_dir()
+        .ok_or_else(|| AppError::Io("could not get resource dir".to_string()))
+        .log_context(|| "could not resolve app paths")
+}
 
-    // NOTE: for databases, where the bad migration code already ran
-    migration_legacy_entity(&tx)?;
+#[tauri::command]
+fn load_encounters_preview(
+    database: tauri::State<Database>,
+    page: i32,
+    page_size: i32,
+    search: String,
+    filter: SearchFilter,
+) -> Result<EncountersOverview, AppError> {
+    let conn = database
+        .reader()
+        .log_context(|| "could not get db connection")?;
 
-    if !stmt.exists(["table", "encounter_preview"])? {
-        info!("optimizing searches");
-        migration_legacy_encounter(&tx)?;
-        migration_legacy_entity(&tx)?;
-        migration_full_text_search(&tx)?;
-    }
+    let order = if filter.order == 1 { "ASC" } else { "DESC" };
+    let sort = format!("e.{}", filter.sort);
+    let (join_clause, where_clause, mut params) = build_encounter_filter(&search, filter);
 
-    if !stmt.exists(["table", "sync_logs"])? {
-        info!("adding sync table");
-        migration_sync(&tx)?;
-    }
+    let count_params = params.clone();
 
-    migration_specs(&tx)?;
+    let query = format!(
+        "SELECT
+    e.id,
+    e.fight_start,
+    e.current_boss,
+    e.duration,
+    e.difficulty,
+    e.favorite,
+    e.cleared,
+    e.local_player,
+    e.my_dps,
+    e.players,
+    e.raid,
+    e.gate
+    FROM encounter_preview e {}
+    {}
+    ORDER BY {} {}
+    LIMIT ?
+    OFFSET ?",
+        join_clause, where_clause, sort, order
+    );
 
-    stmt.finalize()?;
-    info!("finished setting up database");
-    tx.commit()
-}
+    let mut stmt = conn
+        .prepare_cached(&query)
+        .log_context(|| "could not prepare encounter query")?;
 
-fn migration_legacy_encounter(tx: &Transaction) -> Result<(), rusqlite::Error> {
-    tx.execute_batch(&format!(
-        "
-    CREATE TABLE IF NOT EXISTS encounter (
-        id INTEGER PRIMARY KEY,
-        last_combat_packet INTEGER,
-        fight_start INTEGER,
-        local_player TEXT,
-        current_boss TEXT,
-        duration INTEGER,
-        total_damage_dealt INTEGER,
-        top_damage_dealt INTEGER,
-        total_damage_taken INTEGER,
-        top_damage_taken INTEGER,
-        dps INTEGER,
-        buffs TEXT,
-        debuffs TEXT,
-        total_shielding INTEGER DEFAULT 0,
-        total_effective_shielding INTEGER DEFAULT 0,
-        applied_shield_buffs TEXT,
-        misc TEXT,
-        difficulty TEXT,
-        favorite BOOLEAN NOT NULL DEFAULT 0,
-        cleared BOOLEAN,
-        version INTEGER NOT NULL DEFAULT {},
-        boss_only_damage BOOLEAN NOT NULL DEFAULT 0
-    );
-    CREATE INDEX IF NOT EXISTS encounter_fight_start_index
-    ON encounter (fight_start desc);
-    CREATE INDEX IF NOT EXISTS encounter_current_boss_index
-    ON encounter (current_boss);
-    ",
-        DB_VERSION
-    ))?;
+    let offset = (page - 1) * page_size;
 
-    let mut stmt = tx.prepare("SELECT 1 FROM pragma_table_info(?) WHERE name=?")?;
-    if !stmt.exists(["encounter", "misc"])? {
-        tx.execute("ALTER TABLE encounter ADD COLUMN misc TEXT", [])?;
-    }
-    if !stmt.exists(["encounter", "difficulty"])? {
-        tx.execute("ALTER TABLE encounter ADD COLUMN difficulty TEXT", [])?;
-    }
-    if !stmt.exists(["encounter", "favorite"])? {
-        tx.execute_batch(&format!(
-            "
-            ALTER TABLE encounter ADD COLUMN favorite BOOLEAN DEFAULT 0;
-            ALTER TABLE encounter ADD COLUMN version INTEGER DEFAULT {};
-            ALTER TABLE encounter ADD COLUMN cleared BOOLEAN;
-            ",
-            DB_VERSION,
-        ))?;
-    }
-    if !stmt.exists(["encounter", "boss_only_damage"])? {
-        tx.execute(
-            "ALTER TABLE encounter ADD COLUMN boss_only_damage BOOLEAN NOT NULL DEFAULT 0",
-            [],
-        )?;
-    }
-    if !stmt.exists(["encounter", "total_shielding"])? {
-        tx.execute_batch(
-            "
-                ALTER TABLE encounter ADD COLUMN total_shielding INTEGER DEFAULT 0;
-                ALTER TABLE encounter ADD COLUMN total_effective_shielding INTEGER DEFAULT 0;
-                ALTER TABLE encounter ADD COLUMN applied_shield_buffs TEXT;
-                ",
-        )?;
-    }
-    tx.execute("UPDATE encounter SET cleared = coalesce(json_extract(misc, '$.raidClear'), 0) WHERE cleared IS NULL;", [])?;
-    stmt.finalize()
-}
+    params.push(page_size.to_string());
+    params.push(offset.to_string());
 
-fn migration_legacy_entity(tx: &Transaction) -> Result<(), rusqlite::Error> {
-    tx.execute_batch(
-        "
-        CREATE TABLE IF NOT EXISTS entity (
-            name TEXT,
-            character_id INTEGER,
-            encounter_id INTEGER NOT NULL,
-            npc_id INTEGER,
-            entity_type TEXT,
-            class_id INTEGER,
-            class TEXT,
-            gear_score REAL,
-            current_hp INTEGER,
-            max_hp INTEGER,
-            is_dead INTEGER,
-            skills TEXT,
-            damage_stats TEXT,
-            dps INTEGER,
-            skill_stats TEXT,
-            last_update INTEGER,
-            engravings TEXT,
-            PRIMARY KEY (name, encounter_id),
-            FOREIGN KEY (encounter_id) REFERENCES encounter (id) ON DELETE CASCADE
-        );
-        CREATE INDEX IF NOT EXISTS entity_encounter_id_index
-        ON entity (encounter_id desc);
-        CREATE INDEX IF NOT EXISTS entity_name_index
-        ON entity (name);
-        CREATE INDEX IF NOT EXISTS entity_class_index
-        ON entity (class);
-        ",
-    )?;
+    let encounter_iter = stmt
+        .query_map(params_from_iter(params), |row| {
+            let classes: String = row.get(9).unwrap_or_default();
 
-    let mut stmt = tx.prepare("SELECT 1 FROM pragma_table_info(?) WHERE name=?")?;
-    if !stmt.exists(["entity", "dps"])? {
-        tx.execute("ALTER TABLE entity ADD COLUMN dps INTEGER", [])?;
-    }
-    if !stmt.exists(["entity", "character_id"])? {
-        tx.execute("ALTER TABLE entity ADD COLUMN character_id INTEGER", [])?;
-    }
-    if !stmt.exists(["entity", "engravings"])? {
-        tx.execute("ALTER TABLE entity ADD COLUMN engravings TEXT", [])?;
-    }
-    if !stmt.exists(["entity", "gear_hash"])? {
-        tx.execute("ALTER TABLE entity ADD COLUMN gear_hash TEXT", [])?;
-    }
-    tx.execute("UPDATE entity SET dps = coalesce(json_extract(damage_stats, '$.dps'), 0) WHERE dps IS NULL;", [])?;
-    stmt.finalize()
-}
+            let (classes, names) = classes
+                .split(',')
+                .map(|s| {
+                    let info: Vec<&str> = s.split(':').collect();
+                    if info.len() != 2 {
+                        return (101, "Unknown".to_string());
+                    }
+                    (info[0].parse::<i32>().unwrap_or(101), info[1].to_string())
+                })
+                .unzip();
 
-fn migration_full_text_search(tx: &Transaction) -> Result<(), rusqlite::Error> {
-    tx.execute_batch(
-        "
-        CREATE TABLE encounter_preview (
-            id INTEGER PRIMARY KEY,
-            fight_start INTEGER,
-            current_boss TEXT,
-            duration INTEGER,
-            players TEXT,
-            difficulty TEXT,
-            local_player TEXT,
-            my_dps INTEGER,
-            favorite BOOLEAN NOT NULL DEFAULT 0,
-            cleared BOOLEAN,
-            boss_only_damage BOOLEAN NOT NULL DEFAULT 0,
-            FOREIGN KEY (id) REFERENCES encounter(id) ON DELETE CASCADE
-        );
-
-        INSERT INTO encounter_preview SELECT
-            id, fight_start, current_boss, duration, 
-            (
-                SELECT GROUP_CONCAT(class_id || ':' || name ORDER BY dps DESC)
-                FROM entity
-                WHERE encounter_id = encounter.id AND entity_type = 'PLAYER'
-            ) AS players,
-            difficulty, local_player,
-            (
-                SELECT dps
-                FROM entity
-                WHERE encounter_id = encounter.id AND name = encounter.local_player
-            ) AS my_dps,
-            favorite, cleared, boss_only_damage
-        FROM encounter;
-
-        DROP INDEX IF EXISTS encounter_fight_start_index;
-        DROP INDEX IF EXISTS encounter_current_boss_index;
-        DROP INDEX IF EXISTS encounter_favorite_index;
-        DROP INDEX IF EXISTS entity_name_index;
-        DROP INDEX IF EXISTS entity_class_index;
-
-        ALTER TABLE encounter DROP COLUMN fight_start;
-        ALTER TABLE encounter DROP COLUMN current_boss;
-        ALTER TABLE encounter DROP COLUMN duration;
-        ALTER TABLE encounter DROP COLUMN difficulty;
-        ALTER TABLE encounter DROP COLUMN local_player;
-        ALTER TABLE encounter DROP COLUMN favorite;
-        ALTER TABLE encounter DROP COLUMN cleared;
-        ALTER TABLE encounter DROP COLUMN boss_only_damage;
-
-        ALTER TABLE encounter ADD COLUMN boss_hp_log BLOB;
-        ALTER TABLE encounter ADD COLUMN stagger_log TEXT;
-
-        CREATE INDEX encounter_preview_favorite_index ON encounter_preview(favorite);
-        CREATE INDEX encounter_preview_fight_start_index ON encounter_preview(fight_start);
-        CREATE INDEX encounter_preview_my_dps_index ON encounter_preview(my_dps);
-        CREATE INDEX encounter_preview_duration_index ON encounter_preview(duration);
-
-        CREATE VIRTUAL TABLE encounter_search USING fts5(
-            current_boss, players, columnsize=0, detail=full,
-            tokenize='trigram remove_diacritics 1',
-            content=encounter_preview, content_rowid=id
-        );
-        INSERT INTO encounter_search(encounter_search) VALUES('rebuild');
-        CREATE TRIGGER encounter_preview_ai AFTER INSERT ON encounter_preview BEGIN
-            INSERT INTO encounter_search(rowid, current_boss, players)
-            VALUES (new.id, new.current_boss, new.players);
-        END;
-        CREATE TRIGGER encounter_preview_ad AFTER DELETE ON encounter_preview BEGIN
-            INSERT INTO encounter_search(encounter_search, rowid, current_boss, players)
-            VALUES('delete', old.id, old.current_boss, old.players);
-        END;
-        CREATE TRIGGER encounter_preview_au AFTER UPDATE OF current_boss, players ON encounter_preview BEGIN
-            INSERT INTO encounter_search(encounter_search, rowid, current_boss, players)
-            VALUES('delete', old.id, old.current_boss, old.players);
-            INSERT INTO encounter_search(rowid, current_boss, players)
-            VALUES (new.id, new.current_boss, new.players);
-        END;
-        ",
-    )
-}
+            Ok(EncounterPreview {
+                id: row.get(0)?,
+                fight_start: row.get(1)?,
+                boss_name: row.get(2)?,
+                duration: row.get(3)?,
+                classes,
+                names,
+                difficulty: row.get(4)?,
+                favorite: row.get(5)?,
+                cleared: row.get(6)?,
+                local_player: row.get(7)?,
+                my_dps: row.get(8).unwrap_or(0),
+                raid: row.get(10)?,
+                gate: row.get(11)?,
+            })
+        })
+        .log_context(|| "could not query encounters")?;
 
-fn migration_sync(tx: &Transaction) -> Result<(), rusqlite::Error> {
-    tx.execute_batch(
+    let encounters: Vec<EncounterPreview> = encounter_iter
+        .collect::<Result<_, _>>()
+        .log_context(|| "could not read encounters")?;
+
+    let query = format!(
         "
-        CREATE TABLE IF NOT EXISTS sync_logs (
-        encounter_id INTEGER PRIMARY KEY,
-        upstream_id TEXT,
-        failed BOOLEAN NOT NULL DEFAULT 0,
-        FOREIGN KEY (encounter_id) REFERENCES encounter (id) ON DELETE CASCADE
-    );",
-    )
-}
+        SELECT COUNT(*)
+        FROM encounter_preview e {}
+        {}
+        ",
+        join_clause, where_clause
+    );
 
-fn migration_specs(tx: &Transaction) -> Result<(), rusqlite::Error> {
-    let mut stmt = tx.prepare("SELECT 1 FROM pragma_table_info(?) WHERE name=?")?;
-    if !stmt.exists(["entity", "spec"])? {
-        info!("adding spec info columns");
-        tx.execute_batch(
-            "
-                ALTER TABLE entity ADD COLUMN spec TEXT;
-                ALTER TABLE entity ADD COLUMN ark_passive_active BOOLEAN;
-                ALTER TABLE entity ADD COLUMN ark_passive_data TEXT;
-                ",
-        )?;
-    }
+    let count: i32 = conn
+        .query_row_and_then(&query, params_from_iter(count_params), |row| row.get(0))
+        .log_context(|| "could not get encounter count")?;
 
-    stmt.finalize()
+    Ok(EncountersOverview {
+        encounters,
+        total_encounters: count,
+    })
 }
 
-#[tauri::command]
-fn load_encounters_preview(
-    window: tauri::Window,
-    page: i32,
-    page_size: i32,
-    search: String,
+// join and where clauses with their params for the encounter list search and filters
+fn build_encounter_filter(
+    search: &str,
     filter: SearchFilter,
-) -> EncountersOverview {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
+) -> (&'static str, String, Vec<String>) {
     let mut params = vec![];
 
     let join_clause = if search.len() > 2 {
@@ -647,6 +582,15 @@ fn load_encounters_preview(
         "".to_string()
     };
 
+    let raid_filter = if !filter.raids.is_empty() {
+        let mut placeholders = "?,".repeat(filter.raids.len());
+        placeholders.pop(); // remove trailing comma
+        params.extend(filter.raids);
+        format!("AND e.raid IN ({})", placeholders)
+    } else {
+        "".to_string()
+    };
+
     let raid_clear_filter = if filter.cleared {
         "AND cleared = 1"
     } else {
@@ -672,112 +616,235 @@ fn load_encounters_preview(
         ""
     };
 
-    let order = if filter.order == 1 { "ASC" } else { "DESC" };
-    let sort = format!("e.{}", filter.sort);
-
-    let count_params = params.clone();
+    // the trash is only listed when asked for
+    let trash_filter = if filter.trashed {
+        "AND e.deleted_at IS NOT NULL"
+    } else {
+        "AND e.deleted_at IS NULL"
+    };
 
-    let query = format!(
-        "SELECT
-    e.id,
-    e.fight_start,
-    e.current_boss,
-    e.duration,
-    e.difficulty,
-    e.favorite,
-    e.cleared,
-    e.local_player,
-    e.my_dps,
-    e.players
-    FROM encounter_preview e {}
-    WHERE e.duration > ? {}
-    {} {} {} {}
-    ORDER BY {} {}
-    LIMIT ?
-    OFFSET ?",
-        join_clause,
+    let where_clause = format!(
+        "WHERE e.duration > ? {} {}
+    {} {} {} {} {}",
         boss_filter,
+        raid_filter,
         raid_clear_filter,
         favorite_filter,
         difficulty_filter,
         boss_only_damage_filter,
-        sort,
-        order
+        trash_filter
     );
 
-    let mut stmt = conn.prepare_cached(&query).unwrap();
-
-    let offset = (page - 1) * page_size;
+    (join_clause, where_clause, params)
+}
 
-    params.push(page_size.to_string());
-    params.push(offset.to_string());
+#[tauri::command(async)]
+fn load_encounter(database: tauri::State<Database>, id: String) -> Result<Encounter, AppError> {
+    let conn = database
+        .reader()
+        .log_context(|| "could not get db connection")?;
+    read_encounter(&conn, &id).log_context(|| format!("could not load encounter {}", id))
+}
 
-    let encounter_iter = stmt
-        .query_map(params_from_iter(params), |row| {
-            let classes: String = row.get(9).unwrap_or_default();
+#[tauri::command(async)]
+fn export_encounter(
+    window: tauri::Window,
+    database: tauri::State<Database>,
+    id: String,
+) -> Result<String, AppError> {
+    let path = get_resource_path(&window)?;
+    let conn = database
+        .reader()
+        .log_context(|| "could not get db connection")?;
+    let meter_version = window.app_handle().package_info().version.to_string();
+
+    let export = parser::export_encounter_data(&conn, &id, meter_version)
+        .log_context(|| format!("could not export encounter {}", id))?;
+
+    let file_path = path.join("exports").join(format!(
+        "encounter_{}_{}.json.gz",
+        id, export.encounter.fight_start
+    ));
+    write_encounter_export(&file_path, &export)
+        .log_context(|| format!("could not write encounter export {:?}", file_path))?;
+    Ok(file_path.to_string_lossy().to_string())
+}
 
-            let (classes, names) = classes
-                .split(',')
-                .map(|s| {
-                    let info: Vec<&str> = s.split(':').collect();
-                    if info.len() != 2 {
-                        return (101, "Unknown".to_string());
-                    }
-                    (info[0].parse::<i32>().unwrap_or(101), info[1].to_string())
-                })
-                .unzip();
+#[tauri::command(async)]
+fn import_encounter(database: tauri::State<Database>, path: String) -> Result<i64, AppError> {
+    let export = read_encounter_export(Path::new(&path))
+        .log_context(|| format!("could not read encounter export {}", path))?;
+    if export.version > ENCOUNTER_EXPORT_VERSION {
+        let e = AppError::Corrupt(format!(
+            "encounter export {} is version {}, only up to {} is supported",
+            path, export.version, ENCOUNTER_EXPORT_VERSION
+        ));
+        warn!("{}", e);
+        return Err(e);
+    }
 
-            Ok(EncounterPreview {
-                id: row.get(0)?,
-                fight_start: row.get(1)?,
-                boss_name: row.get(2)?,
-                duration: row.get(3)?,
-                classes,
-                names,
-                difficulty: row.get(4)?,
-                favorite: row.get(5)?,
-                cleared: row.get(6)?,
-                local_player: row.get(7)?,
-                my_dps: row.get(8).unwrap_or(0),
-            })
+    let encounter_id = database
+        .write(move |conn| {
+            let tx = conn.transaction()?;
+            let encounter_id = parser::import_encounter_data(&tx, &export)?;
+            tx.commit()?;
+            Ok(encounter_id)
         })
-        .expect("could not query encounters");
+        .log_context(|| format!("could not import encounter {}", path))?;
+    info!("imported encounter {} from {}", encounter_id, path);
 
-    let encounters: Vec<EncounterPreview> = encounter_iter.collect::<Result<_, _>>().unwrap();
+    Ok(encounter_id)
+}
 
-    let query = format!(
-        "
-        SELECT COUNT(*)
-        FROM encounter_preview e {}
-        WHERE duration > ? {}
-        {} {} {} {}
-        ",
-        join_clause,
-        boss_filter,
-        raid_clear_filter,
-        favorite_filter,
-        difficulty_filter,
-        boss_only_damage_filter
-    );
+fn write_encounter_export(
+    path: &Path,
+    export: &EncounterExport,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut encoder = GzEncoder::new(File::create(path)?, Compression::default());
+    serde_json::to_writer(&mut encoder, export)?;
+    encoder.finish()?;
+    Ok(())
+}
 
-    let count: i32 = conn
-        .query_row_and_then(&query, params_from_iter(count_params), |row| row.get(0))
-        .expect("could not get encounter count");
+fn read_encounter_export(path: &Path) -> Result<EncounterExport, Box<dyn std::error::Error>> {
+    let mut decoder = GzDecoder::new(File::open(path)?);
+    let mut contents = String::new();
+    decoder.read_to_string(&mut contents)?;
+    let export = serde_json::from_str(&contents)?;
+    Ok(export)
+}
 
-    EncountersOverview {
-        encounters,
-        total_encounters: count,
+// writes player, skill and buff tables as csv (and optionally a workbook) for the given
+// encounters, or every encounter matching the search when a filter is passed
+#[tauri::command(async)]
+fn export_encounters(
+    window: tauri::Window,
+    database: tauri::State<Database>,
+    ids: Vec<String>,
+    search: String,
+    filter: Option<SearchFilter>,
+    columns: HashMap<String, Vec<String>>,
+    xlsx: bool,
+) -> Result<String, AppError> {
+    let path = get_resource_path(&window)?;
+    let conn = database
+        .reader()
+        .log_context(|| "could not get db connection")?;
+
+    let ids = get_export_ids(&conn, ids, &search, filter)
+        .log_context(|| "could not get encounters to export")?;
+    if ids.is_empty() {
+        return Err(AppError::NotFound("no encounters to export".to_string()));
+    }
+
+    let mut tables = export::EncounterTables::default();
+    for id in ids.iter() {
+        let encounter = read_encounter(&conn, id)
+            .log_context(|| format!("could not load encounter {}", id))?;
+        tables.add_encounter(id, &encounter);
     }
+
+    let dir = path
+        .join("exports")
+        .join(format!("encounters_{}", Utc::now().format("%Y%m%d_%H%M%S")));
+    export::write_tables(&dir, &tables.into_tables(&columns), xlsx)
+        .log_context(|| format!("could not write encounter tables {:?}", dir))?;
+    info!("exported {} encounters to {:?}", ids.len(), dir);
+    Ok(dir.to_string_lossy().to_string())
 }
 
+// per hit rows from the skill cast logs as a single parquet file, for analysis outside the app
 #[tauri::command(async)]
-fn load_encounter(window: tauri::Window, id: String) -> Encounter {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
+fn export_skill_hits(
+    window: tauri::Window,
+    database: tauri::State<Database>,
+    ids: Vec<String>,
+    search: String,
+    filter: Option<SearchFilter>,
+) -> Result<String, AppError> {
+    let path = get_resource_path(&window)?;
+    let conn = database
+        .reader()
+        .log_context(|| "could not get db connection")?;
+
+    let ids = get_export_ids(&conn, ids, &search, filter)
+        .log_context(|| "could not get encounters to export")?;
+    if ids.is_empty() {
+        return Err(AppError::NotFound("no encounters to export".to_string()));
+    }
+
+    let file_path = path.join("exports").join(format!(
+        "skill_hits_{}.parquet",
+        Utc::now().format("%Y%m%d_%H%M%S")
+    ));
+    let rows = write_skill_hits(&conn, &file_path, &ids)
+        .log_context(|| format!("could not write skill hits {:?}", file_path))?;
+    info!("exported {} hits from {} encounters to {:?}", rows, ids.len(), file_path);
+    Ok(file_path.to_string_lossy().to_string())
+}
+
+fn write_skill_hits(
+    conn: &Connection,
+    path: &Path,
+    ids: &[String],
+) -> Result<usize, Box<dyn std::error::Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut writer = export::HitWriter::new(File::create(path)?)?;
+    for id in ids.iter() {
+        let encounter = read_encounter(conn, id)?;
+        writer.add_encounter(id, &encounter)?;
+    }
+    Ok(writer.finish()?)
+}
+
+// the given ids, or every encounter matching the search when a filter is passed
+fn get_export_ids(
+    conn: &Connection,
+    ids: Vec<String>,
+    search: &str,
+    filter: Option<SearchFilter>,
+) -> Result<Vec<String>, rusqlite::Error> {
+    let Some(filter) = filter else {
+        return Ok(ids);
+    };
+
+    let (join_clause, where_clause, params) = build_encounter_filter(search, filter);
+    let query = format!(
+        "SELECT e.id FROM encounter_preview e {} {} ORDER BY e.fight_start",
+        join_clause, where_clause
+    );
+    let mut stmt = conn.prepare(&query)?;
+    let ids = stmt
+        .query_map(params_from_iter(params), |row| {
+            row.get::<_, i64>(0).map(|id| id.to_string())
+        })?
+        .collect::<Result<_, _>>()?;
+    Ok(ids)
+}
+
+#[tauri::command(async)]
+fn compare_encounters(
+    database: tauri::State<Database>,
+    left_id: String,
+    right_id: String,
+) -> Result<EncounterComparison, AppError> {
+    let conn = database
+        .reader()
+        .log_context(|| "could not get db connection")?;
+    let left = read_encounter(&conn, &left_id)
+        .log_context(|| format!("could not load encounter {}", left_id))?;
+    let right = read_encounter(&conn, &right_id)
+        .log_context(|| format!("could not load encounter {}", right_id))?;
+
+    Ok(analysis::compare::compare_encounters(&left_id, &left, &right_id, &right))
+}
+
+fn read_encounter(conn: &Connection, id: &str) -> Result<Encounter, AppError> {
     let mut encounter_stmt = conn
         .prepare_cached(
             "
@@ -802,20 +869,19 @@ fn load_encounter(window: tauri::Window, id: String) -> Encounter {
        total_effective_shielding,
        applied_shield_buffs,
        boss_hp_log,
-       stagger_log
+       stagger_log,
+       raid,
+       gate
     FROM encounter JOIN encounter_preview USING (id)
     WHERE id = ?
     ",
-        )
-        .unwrap();
+        )?;
 
     let mut compressed = false;
     let mut encounter = encounter_stmt
         .query_row(params![id], |row| {
             let misc_str: String = row.get(12).unwrap_or_default();
-            let misc = serde_json::from_str::<EncounterMisc>(misc_str.as_str())
-                .map(Some)
-                .unwrap_or_default();
+            let misc = parse_column::<Option<EncounterMisc>>(12, &misc_str)?;
 
             let mut boss_hp_log: HashMap<String, Vec<BossHpLog>> = HashMap::new();
             let mut stagger_stats: Option<StaggerStats> = None;
@@ -846,61 +912,27 @@ fn load_encounter(window: tauri::Window, id: String) -> Encounter {
             let debuffs: HashMap<u32, StatusEffect>;
             let applied_shield_buffs: HashMap<u32, StatusEffect>;
             if compressed {
-                let raw_bytes: Vec<u8> = row.get(10).unwrap_or_default();
-                let mut decompress = GzDecoder::new(raw_bytes.as_slice());
-                let mut buff_string = String::new();
-                decompress
-                    .read_to_string(&mut buff_string)
-                    .expect("could not decompress buffs");
-                buffs = serde_json::from_str::<HashMap<u32, StatusEffect>>(buff_string.as_str())
-                    .unwrap_or_default();
+                let buff_string = decompress_column(row, 10)?;
+                buffs = parse_column(10, &buff_string)?;
+
+                let debuff_string = decompress_column(row, 11)?;
+                debuffs = parse_column(11, &debuff_string)?;
+
+                let applied_shield_buff_string = decompress_column(row, 19)?;
+                applied_shield_buffs = parse_column(19, &applied_shield_buff_string)?;
 
-                let raw_bytes: Vec<u8> = row.get(11).unwrap_or_default();
-                let mut decompress = GzDecoder::new(raw_bytes.as_slice());
-                let mut debuff_string = String::new();
-                decompress
-                    .read_to_string(&mut debuff_string)
-                    .expect("could not decompress debuffs");
-                debuffs =
-                    serde_json::from_str::<HashMap<u32, StatusEffect>>(debuff_string.as_str())
-                        .unwrap_or_default();
-
-                let raw_bytes: Vec<u8> = row.get(19).unwrap_or_default();
-                let mut decompress = GzDecoder::new(raw_bytes.as_slice());
-                let mut applied_shield_buff_string = String::new();
-                decompress
-                    .read_to_string(&mut applied_shield_buff_string)
-                    .expect("could not decompress applied_shield_buffs");
-                applied_shield_buffs = serde_json::from_str::<HashMap<u32, StatusEffect>>(
-                    applied_shield_buff_string.as_str(),
-                )
-                .unwrap_or_default();
-
-                let raw_bytes: Vec<u8> = row.get(20).unwrap_or_default();
-                let mut decompress = GzDecoder::new(raw_bytes.as_slice());
-                let mut boss_string = String::new();
-                decompress
-                    .read_to_string(&mut boss_string)
-                    .expect("could not decompress boss_hp_log");
-                boss_hp_log =
-                    serde_json::from_str::<HashMap<String, Vec<BossHpLog>>>(boss_string.as_str())
-                        .unwrap_or_default();
+                let boss_string = decompress_column(row, 20)?;
+                boss_hp_log = parse_column(20, &boss_string)?;
 
                 let stagger_str: String = row.get(21).unwrap_or_default();
-                stagger_stats = serde_json::from_str::<Option<StaggerStats>>(stagger_str.as_str())
-                    .unwrap_or_default();
+                stagger_stats = parse_column(21, &stagger_str)?;
             } else {
                 let buff_str: String = row.get(10).unwrap_or_default();
-                buffs = serde_json::from_str::<HashMap<u32, StatusEffect>>(buff_str.as_str())
-                    .unwrap_or_default();
+                buffs = parse_column(10, &buff_str)?;
                 let debuff_str: String = row.get(11).unwrap_or_default();
-                debuffs = serde_json::from_str::<HashMap<u32, StatusEffect>>(debuff_str.as_str())
-                    .unwrap_or_default();
+                debuffs = parse_column(11, &debuff_str)?;
                 let applied_shield_buff_str: String = row.get(19).unwrap_or_default();
-                applied_shield_buffs = serde_json::from_str::<HashMap<u32, StatusEffect>>(
-                    applied_shield_buff_str.as_str(),
-                )
-                .unwrap_or_default();
+                applied_shield_buffs = parse_column(19, &applied_shield_buff_str)?;
             }
 
             let total_shielding = row.get(17).unwrap_or_default();
@@ -932,13 +964,27 @@ fn load_encounter(window: tauri::Window, id: String) -> Encounter {
                 favorite: row.get(14)?,
                 cleared: row.get(15)?,
                 boss_only_damage: row.get(16)?,
+                raid: row.get(22)?,
+                gate: row.get(23)?,
                 ..Default::default()
             })
         })
-        .unwrap_or_else(|_| Encounter::default());
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => {
+                AppError::NotFound(format!("encounter {} does not exist", id))
+            }
+            e => e.into(),
+        })?;
+
+    // entities of archived encounters are in the archive db, attached to every connection
+    let entity_table = if archive::is_archived(conn, id) {
+        "archive.entity"
+    } else {
+        "entity"
+    };
 
     let mut entity_stmt = conn
-        .prepare_cached(
+        .prepare_cached(&format!(
             "
     SELECT name,
         class_id,
@@ -957,12 +1003,13 @@ fn load_encounter(window: tauri::Window, id: String) -> Encounter {
         engravings,
         spec,
         ark_passive_active,
-        ark_passive_data
-    FROM entity
+        ark_passive_data,
+        entity_key
+    FROM {}
     WHERE encounter_id = ?;
     ",
-        )
-        .unwrap();
+            entity_table
+        ))?;
 
     let entity_iter = entity_stmt
         .query_map(params![id], |row| {
@@ -970,52 +1017,37 @@ fn load_encounter(window: tauri::Window, id: String) -> Encounter {
             let damage_stats: DamageStats;
 
             if compressed {
-                let raw_bytes: Vec<u8> = row.get(7).unwrap_or_default();
-                let mut decompress = GzDecoder::new(raw_bytes.as_slice());
-                let mut skill_string = String::new();
-                decompress
-                    .read_to_string(&mut skill_string)
-                    .expect("could not decompress skills");
-                skills = serde_json::from_str::<HashMap<u32, Skill>>(skill_string.as_str())
-                    .unwrap_or_default();
+                let skill_string = decompress_column(row, 7)?;
+                skills = parse_column(7, &skill_string)?;
 
-                let raw_bytes: Vec<u8> = row.get(8).unwrap_or_default();
-                let mut decompress = GzDecoder::new(raw_bytes.as_slice());
-                let mut damage_stats_string = String::new();
-                decompress
-                    .read_to_string(&mut damage_stats_string)
-                    .expect("could not decompress damage stats");
-                damage_stats = serde_json::from_str::<DamageStats>(damage_stats_string.as_str())
-                    .unwrap_or_default();
+                let damage_stats_string = decompress_column(row, 8)?;
+                damage_stats = parse_column(8, &damage_stats_string)?;
             } else {
                 let skill_str: String = row.get(7).unwrap_or_default();
-                skills = serde_json::from_str::<HashMap<u32, Skill>>(skill_str.as_str())
-                    .unwrap_or_default();
+                skills = parse_column(7, &skill_str)?;
 
                 let damage_stats_str: String = row.get(8).unwrap_or_default();
-                damage_stats = serde_json::from_str::<DamageStats>(damage_stats_str.as_str())
-                    .unwrap_or_default();
+                damage_stats = parse_column(8, &damage_stats_str)?;
             }
 
             let skill_stats_str: String = row.get(9).unwrap_or_default();
-            let skill_stats =
-                serde_json::from_str::<SkillStats>(skill_stats_str.as_str()).unwrap_or_default();
+            let skill_stats = parse_column::<SkillStats>(9, &skill_stats_str)?;
 
             let entity_type: String = row.get(11).unwrap_or_default();
 
             let engravings_str: String = row.get(14).unwrap_or_default();
-            let engravings = serde_json::from_str::<Option<Vec<String>>>(engravings_str.as_str())
-                .unwrap_or_default();
+            let engravings = parse_column::<Option<Vec<String>>>(14, &engravings_str)?;
 
             let spec: Option<String> = row.get(15).unwrap_or_default();
             let ark_passive_active: Option<bool> = row.get(16).unwrap_or_default();
 
             let ark_passive_data_str: String = row.get(17).unwrap_or_default();
             let ark_passive_data =
-                serde_json::from_str::<Option<ArkPassiveData>>(ark_passive_data_str.as_str())
-                    .unwrap_or_default();
+                parse_column::<Option<ArkPassiveData>>(17, &ark_passive_data_str)?;
+
+            let key: String = row.get(18)?;
 
-            Ok(EncounterEntity {
+            let entity = EncounterEntity {
                 name: row.get(0)?,
                 class_id: row.get(1)?,
                 class: row.get(2)?,
@@ -1035,13 +1067,15 @@ fn load_encounter(window: tauri::Window, id: String) -> Encounter {
                 ark_passive_active,
                 ark_passive_data,
                 ..Default::default()
-            })
-        })
-        .unwrap();
+            };
+
+            Ok((key, entity))
+        })?;
 
     let mut entities: HashMap<String, EncounterEntity> = HashMap::new();
-    for entity in entity_iter.flatten() {
-        entities.insert(entity.name.to_string(), entity);
+    for row in entity_iter {
+        let (key, entity) = row?;
+        entities.insert(key, entity);
     }
 
     let mut sync_stmt = conn
@@ -1051,25 +1085,226 @@ fn load_encounter(window: tauri::Window, id: String) -> Encounter {
     FROM sync_logs
     WHERE encounter_id = ? AND failed = false;
             ",
-        )
-        .unwrap();
+        )?;
 
     let sync: Result<String, rusqlite::Error> = sync_stmt.query_row(params![id], |row| row.get(0));
     encounter.sync = sync.ok();
 
     encounter.entities = entities;
+    encounter.reindex_entities();
+
+    Ok(encounter)
+}
+
+// empty columns come from older meter versions and are left at their default, anything
+// else that doesn't parse is reported as corrupt instead of showing up as an empty log
+fn parse_column<T: DeserializeOwned + Default>(
+    index: usize,
+    json: &str,
+) -> Result<T, rusqlite::Error> {
+    if json.is_empty() {
+        return Ok(T::default());
+    }
+    serde_json::from_str(json)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e)))
+}
+
+fn decompress_column(row: &rusqlite::Row, index: usize) -> Result<String, rusqlite::Error> {
+    let raw_bytes: Vec<u8> = row.get(index).unwrap_or_default();
+    let mut decompress = GzDecoder::new(raw_bytes.as_slice());
+    let mut contents = String::new();
+    decompress
+        .read_to_string(&mut contents)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Blob, Box::new(e)))?;
+    Ok(contents)
+}
+
+#[tauri::command(async)]
+fn get_encounter_rankings(
+    database: tauri::State<Database>,
+    id: String,
+    gear_score_band: Option<f32>,
+) -> Result<Vec<PlayerRanking>, AppError> {
+    let conn = database
+        .reader()
+        .log_context(|| "could not get db connection")?;
+
+    analysis::ranking::get_encounter_rankings(&conn, &id, gear_score_band)
+        .log_context(|| format!("could not rank encounter {}", id))
+}
+
+#[tauri::command(async)]
+fn get_character_trends(
+    database: tauri::State<Database>,
+    name: String,
+) -> Result<CharacterTrends, AppError> {
+    let conn = database
+        .reader()
+        .log_context(|| "could not get db connection")?;
+
+    analysis::trends::get_character_trends(&conn, &name)
+        .log_context(|| format!("could not load trends for {}", name))
+}
+
+#[tauri::command(async)]
+fn get_roster(
+    window: tauri::Window,
+    database: tauri::State<Database>,
+) -> Result<Vec<RosterCharacter>, AppError> {
+    let path = get_resource_path(&window)?;
+    let local_info = read_local_info(&path).unwrap_or_default();
+    let region = read_region(&path);
+    let conn = database
+        .reader()
+        .log_context(|| "could not get db connection")?;
+
+    analysis::roster::get_roster(
+        &conn,
+        &local_info,
+        region.as_deref(),
+        Utc::now().timestamp_millis(),
+    )
+    .log_context(|| "could not load roster")
+}
+
+#[tauri::command(async)]
+fn get_weekly_clears(
+    window: tauri::Window,
+    database: tauri::State<Database>,
+) -> Result<WeeklyClears, AppError> {
+    let path = get_resource_path(&window)?;
+    load_weekly_clears(&database, &path)
+}
+
+fn load_weekly_clears(database: &Database, resource_path: &Path) -> Result<WeeklyClears, AppError> {
+    let region = read_region(resource_path);
+    let conn = database
+        .reader()
+        .log_context(|| "could not get db connection")?;
+
+    analysis::weekly::get_weekly_clears(&conn, region.as_deref(), Utc::now().timestamp_millis())
+        .log_context(|| "could not load weekly clears")
+}
+
+fn update_tray_summary(app: &tauri::AppHandle) {
+    let Some(path) = app.path_resolver().resource_dir() else {
+        warn!("could not get resource dir to update the tray");
+        return;
+    };
+    let Ok(weekly_clears) = load_weekly_clears(&app.state::<Database>(), &path) else {
+        return;
+    };
+    let total: usize = weekly_clears
+        .characters
+        .iter()
+        .map(|c| c.clears.len())
+        .sum();
+
+    let tray = app.tray_handle();
+    tray.set_tooltip(&analysis::weekly::get_tray_summary(&weekly_clears))
+        .map_err(|e| warn!("could not set tray tooltip: {}", e))
+        .ok();
+    tray.get_item("weekly-clears")
+        .set_title(format!("Weekly Clears ({})", total))
+        .map_err(|e| warn!("could not set tray title: {}", e))
+        .ok();
+}
 
-    encounter
+#[tauri::command(async)]
+fn get_progression(
+    database: tauri::State<Database>,
+    boss: Option<String>,
+) -> Result<Vec<BossProgression>, AppError> {
+    let conn = database
+        .reader()
+        .log_context(|| "could not get db connection")?;
+
+    analysis::progression::get_progression(&conn, boss.as_deref())
+        .log_context(|| "could not load progression")
+}
+
+#[tauri::command(async)]
+fn load_raid_sessions(
+    database: tauri::State<Database>,
+    page: i32,
+    page_size: i32,
+) -> Result<Vec<RaidSession>, AppError> {
+    let conn = database
+        .reader()
+        .log_context(|| "could not get db connection")?;
+
+    analysis::sessions::get_sessions(&conn, page, page_size)
+        .log_context(|| "could not load raid sessions")
+}
+
+#[tauri::command(async)]
+fn load_raid_session(
+    database: tauri::State<Database>,
+    id: i64,
+) -> Result<Option<RaidSession>, AppError> {
+    let conn = database
+        .reader()
+        .log_context(|| "could not get db connection")?;
+
+    analysis::sessions::get_session(&conn, id)
+        .log_context(|| format!("could not load raid session {}", id))
 }
 
 #[tauri::command]
-fn get_sync_candidates(window: tauri::Window, force_resync: bool) -> Vec<i32> {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
+fn load_personal_bests(
+    database: tauri::State<Database>,
+    name: Option<String>,
+) -> Result<Vec<PersonalBest>, AppError> {
+    let conn = database
+        .reader()
+        .log_context(|| "could not get db connection")?;
+    let mut stmt = conn
+        .prepare_cached(
+            "
+    SELECT name,
+        class_id,
+        class,
+        boss,
+        difficulty,
+        kind,
+        value,
+        skill_id,
+        encounter_id,
+        fight_start
+    FROM personal_best
+    WHERE ?1 IS NULL OR name = ?1
+    ORDER BY name, boss, difficulty, kind
+    ",
+        )
+        .log_context(|| "could not prepare personal best query")?;
+
+    stmt.query_map(params![name], |row| {
+        Ok(PersonalBest {
+            name: row.get(0)?,
+            class_id: row.get(1)?,
+            class: row.get(2).unwrap_or_default(),
+            boss: row.get(3)?,
+            difficulty: row.get(4)?,
+            kind: row.get(5)?,
+            value: row.get(6)?,
+            previous: None,
+            skill_id: row.get(7)?,
+            encounter_id: row.get(8)?,
+            fight_start: row.get(9)?,
+        })
+    })
+    .and_then(|rows| rows.collect())
+    .log_context(|| "could not load personal bests")
+}
+
+#[tauri::command]
+fn get_sync_candidates(
+    database: tauri::State<Database>,
+    force_resync: bool,
+) -> Result<Vec<i32>, AppError> {
+    let conn = database
+        .reader()
+        .log_context(|| "could not get db connection")?;
     let query = if force_resync { "= '0'" } else { "IS NULL" };
     let mut stmt = conn
         .prepare_cached(&format!(
@@ -1077,57 +1312,52 @@ fn get_sync_candidates(window: tauri::Window, force_resync: bool) -> Vec<i32> {
     SELECT id
     FROM encounter_preview
     LEFT JOIN sync_logs ON encounter_id = id
-    WHERE cleared = true AND boss_only_damage = 1 AND upstream_id {}
+    WHERE cleared = true AND boss_only_damage = 1 AND upstream_id {} AND deleted_at IS NULL
     ORDER BY fight_start;
             ",
             query
         ))
-        .unwrap();
-    let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+        .log_context(|| "could not prepare sync candidates query")?;
 
-    let mut ids = Vec::new();
-    for id_result in rows {
-        ids.push(id_result.unwrap_or(0));
-    }
-    ids
+    stmt.query_map([], |row| row.get(0))
+        .and_then(|rows| rows.collect())
+        .log_context(|| "could not load sync candidates")
 }
 
 #[tauri::command]
-fn get_encounter_count(window: tauri::Window) -> i32 {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
-    let mut stmt = conn
-        .prepare_cached("SELECT COUNT(*) FROM encounter_preview")
-        .unwrap();
-
-    let count: Result<i32, rusqlite::Error> = stmt.query_row(params![], |row| row.get(0));
-
-    count.unwrap_or(0)
+fn get_encounter_count(database: tauri::State<Database>) -> Result<i32, AppError> {
+    let conn = database
+        .reader()
+        .log_context(|| "could not get db connection")?;
+    conn.query_row(
+        "SELECT COUNT(*) FROM encounter_preview WHERE deleted_at IS NULL",
+        params![],
+        |row| row.get(0),
+    )
+    .log_context(|| "could not get encounter count")
 }
 
 #[tauri::command]
-fn open_most_recent_encounter(window: tauri::Window) {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
+fn open_most_recent_encounter(
+    window: tauri::Window,
+    database: tauri::State<Database>,
+) -> Result<(), AppError> {
+    let conn = database
+        .reader()
+        .log_context(|| "could not get db connection")?;
     let mut stmt = conn
         .prepare_cached(
             "
     SELECT id
     FROM encounter_preview
+    WHERE deleted_at IS NULL
     ORDER BY fight_start DESC
     LIMIT 1;
     ",
         )
-        .unwrap();
+        .log_context(|| "could not prepare most recent encounter query")?;
 
+    // no rows just means there is nothing to open yet
     let id_result: Result<i32, rusqlite::Error> = stmt.query_row(params![], |row| row.get(0));
 
     if let Some(logs) = window.app_handle().get_window(LOGS_WINDOW_LABEL) {
@@ -1140,74 +1370,182 @@ fn open_most_recent_encounter(window: tauri::Window) {
             }
         }
     }
+    Ok(())
 }
 
 #[tauri::command]
-fn toggle_encounter_favorite(window: tauri::Window, id: i32) {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-
-    let conn = get_db_connection(&path).expect("could not get db connection");
-    let mut stmt = conn
-        .prepare_cached(
-            "
+fn toggle_encounter_favorite(database: tauri::State<Database>, id: i32) -> Result<(), AppError> {
+    database
+        .write(move |conn| {
+            let mut stmt = conn.prepare_cached(
+                "
     UPDATE encounter_preview
     SET favorite = NOT favorite
     WHERE id = ?;
     ",
-        )
-        .unwrap();
-
-    stmt.execute(params![id]).unwrap();
+            )?;
+            stmt.execute(params![id])
+        })
+        .log_context(|| "could not toggle favorite")?;
+    Ok(())
 }
 
 #[tauri::command]
-fn delete_encounter(window: tauri::Window, id: String) {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
-    conn.execute("PRAGMA foreign_keys = ON;", params![])
-        .unwrap();
-    let mut stmt = conn
-        .prepare_cached(
-            "
-        DELETE FROM encounter
-        WHERE id = ?;
+fn delete_encounter(database: tauri::State<Database>, id: String) -> Result<(), AppError> {
+    info!("moving encounter to trash: {}", id);
+
+    database
+        .write(move |conn| {
+            let mut stmt = conn.prepare_cached(
+                "
+        UPDATE encounter_preview
+        SET deleted_at = ?
+        WHERE id = ? AND deleted_at IS NULL;
     ",
-        )
-        .unwrap();
+            )?;
+            stmt.execute(params![Utc::now().timestamp_millis(), id])
+        })
+        .log_context(|| "could not delete encounter")?;
+    Ok(())
+}
 
-    info!("deleting encounter: {}", id);
+#[tauri::command]
+fn delete_encounters(
+    window: tauri::Window,
+    database: tauri::State<Database>,
+    ids: Vec<i32>,
+) -> Result<(), AppError> {
+    let path = get_resource_path(&window)?;
+    let placeholders: Vec<String> = ids.iter().map(|_| "?".to_string()).collect();
+    let placeholders_str = placeholders.join(",");
 
-    stmt.execute(params![id]).unwrap();
+    let sql = format!(
+        "UPDATE encounter_preview SET deleted_at = ? WHERE id IN ({}) AND deleted_at IS NULL",
+        placeholders_str
+    );
+    info!("moving encounters to trash: {:?}", ids);
+
+    let mut params = vec![Utc::now().timestamp_millis()];
+    params.extend(ids.iter().map(|id| *id as i64));
+    database
+        .write(move |conn| {
+            backup_before_delete(conn, &path)?;
+            conn.execute(&sql, params_from_iter(params))
+        })
+        .log_context(|| "could not delete encounters")?;
+    Ok(())
 }
 
 #[tauri::command]
-fn delete_encounters(window: tauri::Window, ids: Vec<i32>) {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
-    conn.execute("PRAGMA foreign_keys = ON;", params![])
-        .unwrap();
-
+fn restore_encounters(database: tauri::State<Database>, ids: Vec<i32>) -> Result<(), AppError> {
     let placeholders: Vec<String> = ids.iter().map(|_| "?".to_string()).collect();
     let placeholders_str = placeholders.join(",");
 
-    let sql = format!("DELETE FROM encounter WHERE id IN ({})", placeholders_str);
-    let mut stmt = conn.prepare_cached(&sql).unwrap();
+    let sql = format!(
+        "UPDATE encounter_preview SET deleted_at = NULL WHERE id IN ({})",
+        placeholders_str
+    );
+    info!("restoring encounters from trash: {:?}", ids);
+
+    database
+        .write(move |conn| conn.execute(&sql, params_from_iter(ids)))
+        .log_context(|| "could not restore encounters")?;
+    Ok(())
+}
 
-    info!("deleting encounters: {:?}", ids);
+#[tauri::command]
+fn empty_trash(window: tauri::Window, database: tauri::State<Database>) -> Result<(), AppError> {
+    let path = get_resource_path(&window)?;
+    database
+        .write(move |conn| {
+            backup_before_delete(conn, &path)?;
+
+            let count = delete_trashed_encounters(conn, None)?;
+            info!("emptied trash, deleted {} encounters", count);
+            conn.execute("VACUUM", [])?;
+            Ok(())
+        })
+        .log_context(|| "could not empty trash")
+}
 
-    stmt.execute(params_from_iter(ids)).unwrap();
+// hard deletes trashed encounters, only the ones trashed before the cutoff when given
+fn delete_trashed_encounters(
+    conn: &Connection,
+    trashed_before: Option<i64>,
+) -> Result<usize, rusqlite::Error> {
+    conn.execute("PRAGMA foreign_keys = ON;", [])?;
+    let count = conn.execute(
+        "DELETE FROM encounter
+        WHERE id IN (
+            SELECT id
+            FROM encounter_preview
+            WHERE deleted_at IS NOT NULL AND deleted_at < ?
+        )",
+        params![trashed_before.unwrap_or(i64::MAX)],
+    )?;
+    archive::prune_archive(conn)?;
+    Ok(count)
+}
+
+fn apply_retention_rules(database: &Database, resource_path: &Path) {
+    let Ok(settings) = read_settings(resource_path) else {
+        return;
+    };
+    let now = Utc::now().timestamp_millis();
+    let result =
+        database.write(move |conn| retention::apply_retention(conn, &settings.retention, now));
+    if let Err(e) = result {
+        warn!("could not apply retention rules: {}", e);
+    }
+}
+
+fn archive_old_encounters(database: &Database, resource_path: &Path) {
+    let archive_after_days = read_settings(resource_path)
+        .map(|settings| settings.logs.archive_after_days)
+        .unwrap_or(0);
+    if archive_after_days == 0 {
+        return;
+    }
+
+    let cutoff = Utc::now().timestamp_millis() - archive_after_days as i64 * 24 * 60 * 60 * 1000;
+    let result = database.write(move |conn| archive::archive_encounters(conn, cutoff));
+    if let Err(e) = result {
+        warn!("could not archive encounters: {}", e);
+    }
+}
+
+#[tauri::command(async)]
+fn archive_encounters(
+    database: tauri::State<Database>,
+    older_than_days: u32,
+) -> Result<usize, AppError> {
+    let cutoff = Utc::now().timestamp_millis() - older_than_days as i64 * 24 * 60 * 60 * 1000;
+    database
+        .write(move |conn| {
+            let count = archive::archive_encounters(conn, cutoff)?;
+            if count > 0 {
+                conn.execute("VACUUM", [])?;
+            }
+            Ok(count)
+        })
+        .log_context(|| "could not archive encounters")
+}
+
+fn purge_expired_trash(database: &Database, resource_path: &Path) {
+    let retention_days = read_settings(resource_path)
+        .map(|settings| settings.logs.trash_retention_days)
+        .unwrap_or(0);
+    if retention_days == 0 {
+        return;
+    }
+
+    let cutoff = Utc::now().timestamp_millis() - retention_days as i64 * 24 * 60 * 60 * 1000;
+    let result = database.write(move |conn| delete_trashed_encounters(conn, Some(cutoff)));
+    match result {
+        Ok(0) => (),
+        Ok(count) => info!("deleted {} encounters from trash", count),
+        Err(e) => warn!("could not purge trash: {}", e),
+    }
 }
 
 #[tauri::command]
@@ -1245,16 +1583,13 @@ fn open_url(window: tauri::Window, url: String) {
 }
 
 #[tauri::command]
-fn save_settings(window: tauri::Window, settings: Settings) {
-    let mut path: PathBuf = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
+fn save_settings(window: tauri::Window, settings: Settings) -> Result<(), AppError> {
+    let mut path: PathBuf = get_resource_path(&window)?;
     path.push("settings.json");
-    let mut file = File::create(path).expect("could not create settings file");
-    file.write_all(serde_json::to_string_pretty(&settings).unwrap().as_bytes())
-        .expect("could not write to settings file");
+    let mut file = File::create(path).log_context(|| "could not create settings file")?;
+    let contents = serde_json::to_string_pretty(&settings)?;
+    file.write_all(contents.as_bytes())
+        .log_context(|| "could not write to settings file")
 }
 
 fn read_settings(resource_path: &Path) -> Result<Settings, Box<dyn std::error::Error>> {
@@ -1267,14 +1602,31 @@ fn read_settings(resource_path: &Path) -> Result<Settings, Box<dyn std::error::E
     Ok(settings)
 }
 
+fn read_region(resource_path: &Path) -> Option<String> {
+    let region = fs::read_to_string(resource_path.join("current_region")).ok()?;
+    let region = region.trim();
+    if region.is_empty() {
+        None
+    } else {
+        Some(region.to_string())
+    }
+}
+
+fn read_local_info(resource_path: &Path) -> Result<LocalInfo, Box<dyn std::error::Error>> {
+    let mut path = resource_path.to_path_buf();
+    path.push("local_players.json");
+    let mut file = File::open(path)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    let local_info = serde_json::from_str(&contents)?;
+    Ok(local_info)
+}
+
 #[tauri::command]
-fn get_settings(window: tauri::Window) -> Option<Settings> {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    read_settings(&path).ok()
+fn get_settings(window: tauri::Window) -> Result<Option<Settings>, AppError> {
+    let path = get_resource_path(&window)?;
+    // missing or unreadable settings fall back to the defaults in the ui
+    Ok(read_settings(&path).ok())
 }
 
 #[tauri::command]
@@ -1290,159 +1642,189 @@ fn open_folder(path: String) {
 }
 
 #[tauri::command]
-fn open_db_path(window: tauri::Window) {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
+fn open_db_path(window: tauri::Window) -> Result<(), AppError> {
+    let path = get_resource_path(&window)?;
     info!("open_db_path: {}", path.display());
     Command::new("explorer")
-        .args([path.to_str().unwrap()])
+        .args([path.to_string_lossy()])
         .spawn()
         .ok();
+    Ok(())
 }
 
 #[tauri::command]
 fn delete_encounters_below_min_duration(
     window: tauri::Window,
+    database: tauri::State<Database>,
     min_duration: i64,
     keep_favorites: bool,
-) {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
-    if keep_favorites {
-        conn.execute(
-            "DELETE FROM encounter
-            WHERE id IN (
-                SELECT id
-                FROM encounter_preview
-                WHERE duration < ? AND favorite = 0
-            )",
-            params![min_duration * 1000],
-        )
-        .unwrap();
+) -> Result<(), AppError> {
+    let path = get_resource_path(&window)?;
+    let favorite_filter = if keep_favorites {
+        "AND favorite = 0"
     } else {
-        conn.execute(
-            "DELETE FROM encounter
-            WHERE id IN (
-                SELECT id
-                FROM encounter_preview
-                WHERE duration < ?
-            )",
-            params![min_duration * 1000],
-        )
-        .unwrap();
-    }
-    conn.execute("VACUUM", params![]).unwrap();
+        ""
+    };
+    let sql = format!(
+        "UPDATE encounter_preview
+            SET deleted_at = ?
+            WHERE duration < ? AND deleted_at IS NULL {}",
+        favorite_filter
+    );
+    database
+        .write(move |conn| {
+            backup_before_delete(conn, &path)?;
+            conn.execute(
+                &sql,
+                params![Utc::now().timestamp_millis(), min_duration * 1000],
+            )
+        })
+        .log_context(|| "could not delete encounters")?;
+    Ok(())
 }
 
 #[tauri::command]
-fn sync(window: tauri::Window, encounter: i32, upstream: String, failed: bool) {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
-
-    conn.execute(
-        "
+fn sync(
+    database: tauri::State<Database>,
+    encounter: i32,
+    upstream: String,
+    failed: bool,
+) -> Result<(), AppError> {
+    database
+        .write(move |conn| {
+            conn.execute(
+                "
         INSERT OR REPLACE INTO sync_logs (encounter_id, upstream_id, failed)
         VALUES(?, ?, ?);
         ",
-        params![encounter, upstream, failed],
-    )
-    .unwrap();
+                params![encounter, upstream, failed],
+            )
+        })
+        .log_context(|| "could not save sync log")?;
+    Ok(())
 }
 
 #[tauri::command]
-fn delete_all_uncleared_encounters(window: tauri::Window, keep_favorites: bool) {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
-    if keep_favorites {
-        conn.execute(
-            "DELETE FROM encounter
-            WHERE id IN (
-                SELECT id
-                FROM encounter_preview
-                WHERE cleared = 0 AND favorite = 0
-            )",
-            [],
-        )
-        .unwrap();
+fn delete_all_uncleared_encounters(
+    window: tauri::Window,
+    database: tauri::State<Database>,
+    keep_favorites: bool,
+) -> Result<(), AppError> {
+    let path = get_resource_path(&window)?;
+    let favorite_filter = if keep_favorites {
+        "AND favorite = 0"
     } else {
-        conn.execute(
-            "DELETE FROM encounter
-            WHERE id IN (
-                SELECT id
-                FROM encounter_preview
-                WHERE cleared = 0
-            )",
-            [],
-        )
-        .unwrap();
-    }
-    conn.execute("VACUUM", params![]).unwrap();
+        ""
+    };
+    let sql = format!(
+        "UPDATE encounter_preview
+            SET deleted_at = ?
+            WHERE cleared = 0 AND deleted_at IS NULL {}",
+        favorite_filter
+    );
+    database
+        .write(move |conn| {
+            backup_before_delete(conn, &path)?;
+            conn.execute(&sql, params![Utc::now().timestamp_millis()])
+        })
+        .log_context(|| "could not delete uncleared encounters")?;
+    Ok(())
 }
 
 #[tauri::command]
-fn delete_all_encounters(window: tauri::Window, keep_favorites: bool) {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
-
-    if keep_favorites {
-        conn.execute(
-            "DELETE FROM encounter
+fn delete_all_encounters(
+    window: tauri::Window,
+    database: tauri::State<Database>,
+    keep_favorites: bool,
+) -> Result<(), AppError> {
+    let path = get_resource_path(&window)?;
+    database
+        .write(move |conn| {
+            backup_before_delete(conn, &path)?;
+
+            if keep_favorites {
+                conn.execute(
+                    "DELETE FROM encounter
             WHERE id IN (
                 SELECT id
                 FROM encounter_preview
                 WHERE favorite = 0
             )",
-            [],
-        )
-        .unwrap();
-    } else {
-        conn.execute("DELETE FROM encounter", []).unwrap();
-    }
-    conn.execute("VACUUM", []).unwrap();
+                    [],
+                )?;
+            } else {
+                conn.execute("DELETE FROM encounter", [])?;
+            }
+            if let Err(e) = archive::prune_archive(conn) {
+                warn!("could not prune archive: {}", e);
+            }
+            conn.execute("VACUUM", [])?;
+            Ok(())
+        })
+        .log_context(|| "could not delete encounters")?;
+    Ok(())
+}
+
+// bulk deletes are aborted with this error when the db can't be backed up first
+fn backup_before_delete(conn: &Connection, resource_path: &Path) -> Result<(), rusqlite::Error> {
+    let keep = read_settings(resource_path)
+        .map(|settings| settings.backup.keep)
+        .unwrap_or(backup::DEFAULT_KEEP);
+    backup::backup_database(conn, resource_path, backup::BackupKind::Delete, keep)?;
+    Ok(())
 }
 
 #[tauri::command]
-fn get_db_info(window: tauri::Window, min_duration: i64) -> EncounterDbInfo {
-    let mut path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
+fn list_backups(window: tauri::Window) -> Result<Vec<DbBackup>, AppError> {
+    let path = get_resource_path(&window)?;
+    backup::list_backups(&path).log_context(|| "could not list backups")
+}
+
+#[tauri::command]
+fn restore_backup(
+    window: tauri::Window,
+    database: tauri::State<Database>,
+    name: String,
+) -> Result<(), AppError> {
+    let path = get_resource_path(&window)?;
+    let keep = read_settings(&path)
+        .map(|settings| settings.backup.keep)
+        .unwrap_or(backup::DEFAULT_KEEP);
+    let backup_name = name.clone();
+    database
+        .write(move |conn| Ok(backup::restore_backup(conn, &path, &backup_name, keep)))
+        .map_err(AppError::from)
+        .and_then(|restored| restored.map_err(AppError::from))
+        .log_context(|| format!("could not restore backup {}", name))
+}
+
+#[tauri::command]
+fn get_db_info(
+    window: tauri::Window,
+    database: tauri::State<Database>,
+    min_duration: i64,
+) -> Result<EncounterDbInfo, AppError> {
+    let mut path = get_resource_path(&window)?;
+    let conn = database
+        .reader()
+        .log_context(|| "could not get db connection")?;
     let encounter_count = conn
-        .query_row("SELECT COUNT(*) FROM encounter_preview", [], |row| {
-            row.get(0)
-        })
-        .unwrap();
+        .query_row(
+            "SELECT COUNT(*) FROM encounter_preview WHERE deleted_at IS NULL",
+            [],
+            |row| row.get(0),
+        )
+        .log_context(|| "could not get encounter count")?;
     let encounter_filtered_count = conn
         .query_row(
-            "SELECT COUNT(*) FROM encounter_preview WHERE duration >= ?",
+            "SELECT COUNT(*) FROM encounter_preview WHERE duration >= ? AND deleted_at IS NULL",
             params![min_duration * 1000],
             |row| row.get(0),
         )
-        .unwrap();
+        .log_context(|| "could not get filtered encounter count")?;
 
     path.push("encounters.db");
-    let metadata = fs::metadata(path).expect("could not get db metadata");
+    let metadata = fs::metadata(path).log_context(|| "could not get db metadata")?;
 
     let size_in_bytes = metadata.len();
     let size_in_kb = size_in_bytes as f64 / 1024.0;
@@ -1457,29 +1839,27 @@ fn get_db_info(window: tauri::Window, min_duration: i64) -> EncounterDbInfo {
         format!("{:.2} KB", size_in_kb)
     };
 
-    EncounterDbInfo {
+    Ok(EncounterDbInfo {
         size: size_str,
         total_encounters: encounter_count,
         total_encounters_filtered: encounter_filtered_count,
-    }
+    })
 }
 
 #[tauri::command]
-fn optimize_database(window: tauri::Window) {
-    let path = window
-        .app_handle()
-        .path_resolver()
-        .resource_dir()
-        .expect("could not get resource dir");
-    let conn = get_db_connection(&path).expect("could not get db connection");
-    conn.execute_batch(
-        "
+fn optimize_database(database: tauri::State<Database>) -> Result<(), AppError> {
+    database
+        .write(|conn| {
+            conn.execute_batch(
+                "
         INSERT INTO encounter_search(encounter_search) VALUES('optimize');
         VACUUM;
         ",
-    )
-    .unwrap();
+            )
+        })
+        .log_context(|| "could not optimize database")?;
     info!("optimized database");
+    Ok(())
 }
 
 #[tauri::command]
@@ -1519,7 +1899,12 @@ fn set_clickthrough(window: tauri::Window, set: bool) {
 
 #[tauri::command]
 fn remove_driver() {
-    Command::new("sc").args(["delete", "windivert"]).output().expect("unable to delete driver");
+    Command::new("sc")
+        .args(["delete", "windivert"])
+        .output()
+        .map_err(|e| AppError::Io(e.to_string()))
+        .log_context(|| "unable to delete driver")
+        .ok();
 }
 
 #[tauri::command]
@@ -0,0 +1,673 @@
+use crate::backup::{self, BackupKind};
+use crate::parser::models::*;
+use crate::session_assign;
+use log::info;
+use rusqlite::{params, Connection, Transaction};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+struct Migration {
+    version: i32,
+    name: &'static str,
+    // drops or rebuilds tables, the db is backed up before it runs
+    destructive: bool,
+    run: fn(&Transaction) -> Result<(), rusqlite::Error>,
+}
+
+// applied in order and tracked in PRAGMA user_version
+// append new migrations to the end, never change one that has already shipped
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "legacy_tables",
+        destructive: false,
+        run: migration_legacy_tables,
+    },
+    Migration {
+        version: 2,
+        name: "full_text_search",
+        destructive: true,
+        run: migration_encounter_preview,
+    },
+    Migration {
+        version: 3,
+        name: "sync",
+        destructive: false,
+        run: migration_sync,
+    },
+    Migration {
+        version: 4,
+        name: "specs",
+        destructive: false,
+        run: migration_specs,
+    },
+    Migration {
+        version: 5,
+        name: "entity_key",
+        destructive: true,
+        run: migration_entity_key,
+    },
+    Migration {
+        version: 6,
+        name: "raid_gates",
+        destructive: false,
+        run: migration_raid_gates,
+    },
+    Migration {
+        version: 7,
+        name: "personal_bests",
+        destructive: false,
+        run: migration_personal_bests,
+    },
+    Migration {
+        version: 8,
+        name: "raid_sessions",
+        destructive: false,
+        run: migration_raid_sessions,
+    },
+    Migration {
+        version: 9,
+        name: "trash",
+        destructive: false,
+        run: migration_trash,
+    },
+    Migration {
+        version: 10,
+        name: "archive",
+        destructive: false,
+        run: migration_archive,
+    },
+];
+
+pub fn get_schema_version(conn: &Connection) -> Result<i32, rusqlite::Error> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
+// runs every migration newer than the db's schema version, each in its own transaction
+// a dry run applies them all in one transaction and rolls it back
+// returns the names of the migrations that ran
+pub fn run_migrations(
+    conn: &mut Connection,
+    backup_dir: &Path,
+    dry_run: bool,
+) -> Result<Vec<&'static str>, rusqlite::Error> {
+    let version = get_schema_version(conn)?;
+    let pending: Vec<&Migration> = MIGRATIONS.iter().filter(|m| m.version > version).collect();
+    if pending.is_empty() {
+        return Ok(vec![]);
+    }
+
+    if dry_run {
+        let tx = conn.transaction()?;
+        for migration in pending.iter() {
+            info!("dry run of migration {} ({})", migration.version, migration.name);
+            (migration.run)(&tx)?;
+        }
+        tx.rollback()?;
+        return Ok(pending.iter().map(|m| m.name).collect());
+    }
+
+    if pending.iter().any(|m| m.destructive) && table_exists(conn, "encounter")? {
+        info!("backing up database before migrating from version {}", version);
+        backup::backup_database(conn, backup_dir, BackupKind::Migration, backup::DEFAULT_KEEP)?;
+    }
+
+    let mut applied = vec![];
+    for migration in pending {
+        info!("running migration {} ({})", migration.version, migration.name);
+        let tx = conn.transaction()?;
+        (migration.run)(&tx)?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+        applied.push(migration.name);
+    }
+
+    Ok(applied)
+}
+
+fn table_exists(conn: &Connection, name: &str) -> Result<bool, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT 1 FROM sqlite_master WHERE type=? AND name=?")?;
+    stmt.exists(["table", name])
+}
+
+// databases from before versioning start at 0 and can be anywhere in the legacy chain,
+// so the migrations that shipped before it (1 to 4) still check what is already there.
+// everything after relies on user_version alone
+fn migration_legacy_tables(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    if !table_exists(tx, "encounter")? {
+        info!("creating tables");
+        migration_legacy_encounter(tx)?;
+    }
+    // NOTE: for databases, where the bad migration code already ran
+    migration_legacy_entity(tx)
+}
+
+fn migration_encounter_preview(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    if table_exists(tx, "encounter_preview")? {
+        return Ok(());
+    }
+
+    info!("optimizing searches");
+    migration_legacy_encounter(tx)?;
+    migration_legacy_entity(tx)?;
+    migration_full_text_search(tx)
+}
+
+fn migration_legacy_encounter(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    tx.execute_batch(&format!(
+        "
+    CREATE TABLE IF NOT EXISTS encounter (
+        id INTEGER PRIMARY KEY,
+        last_combat_packet INTEGER,
+        fight_start INTEGER,
+        local_player TEXT,
+        current_boss TEXT,
+        duration INTEGER,
+        total_damage_dealt INTEGER,
+        top_damage_dealt INTEGER,
+        total_damage_taken INTEGER,
+        top_damage_taken INTEGER,
+        dps INTEGER,
+        buffs TEXT,
+        debuffs TEXT,
+        total_shielding INTEGER DEFAULT 0,
+        total_effective_shielding INTEGER DEFAULT 0,
+        applied_shield_buffs TEXT,
+        misc TEXT,
+        difficulty TEXT,
+        favorite BOOLEAN NOT NULL DEFAULT 0,
+        cleared BOOLEAN,
+        version INTEGER NOT NULL DEFAULT {},
+        boss_only_damage BOOLEAN NOT NULL DEFAULT 0
+    );
+    CREATE INDEX IF NOT EXISTS encounter_fight_start_index
+    ON encounter (fight_start desc);
+    CREATE INDEX IF NOT EXISTS encounter_current_boss_index
+    ON encounter (current_boss);
+    ",
+        DB_VERSION
+    ))?;
+
+    let mut stmt = tx.prepare("SELECT 1 FROM pragma_table_info(?) WHERE name=?")?;
+    if !stmt.exists(["encounter", "misc"])? {
+        tx.execute("ALTER TABLE encounter ADD COLUMN misc TEXT", [])?;
+    }
+    if !stmt.exists(["encounter", "difficulty"])? {
+        tx.execute("ALTER TABLE encounter ADD COLUMN difficulty TEXT", [])?;
+    }
+    if !stmt.exists(["encounter", "favorite"])? {
+        tx.execute_batch(&format!(
+            "
+            ALTER TABLE encounter ADD COLUMN favorite BOOLEAN DEFAULT 0;
+            ALTER TABLE encounter ADD COLUMN version INTEGER DEFAULT {};
+            ALTER TABLE encounter ADD COLUMN cleared BOOLEAN;
+            ",
+            DB_VERSION,
+        ))?;
+    }
+    if !stmt.exists(["encounter", "boss_only_damage"])? {
+        tx.execute(
+            "ALTER TABLE encounter ADD COLUMN boss_only_damage BOOLEAN NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
+    if !stmt.exists(["encounter", "total_shielding"])? {
+        tx.execute_batch(
+            "
+                ALTER TABLE encounter ADD COLUMN total_shielding INTEGER DEFAULT 0;
+                ALTER TABLE encounter ADD COLUMN total_effective_shielding INTEGER DEFAULT 0;
+                ALTER TABLE encounter ADD COLUMN applied_shield_buffs TEXT;
+                ",
+        )?;
+    }
+    tx.execute("UPDATE encounter SET cleared = coalesce(json_extract(misc, '$.raidClear'), 0) WHERE cleared IS NULL;", [])?;
+    stmt.finalize()
+}
+
+fn migration_legacy_entity(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS entity (
+            name TEXT,
+            character_id INTEGER,
+            encounter_id INTEGER NOT NULL,
+            npc_id INTEGER,
+            entity_type TEXT,
+            class_id INTEGER,
+            class TEXT,
+            gear_score REAL,
+            current_hp INTEGER,
+            max_hp INTEGER,
+            is_dead INTEGER,
+            skills TEXT,
+            damage_stats TEXT,
+            dps INTEGER,
+            skill_stats TEXT,
+            last_update INTEGER,
+            engravings TEXT,
+            PRIMARY KEY (name, encounter_id),
+            FOREIGN KEY (encounter_id) REFERENCES encounter (id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS entity_encounter_id_index
+        ON entity (encounter_id desc);
+        CREATE INDEX IF NOT EXISTS entity_name_index
+        ON entity (name);
+        CREATE INDEX IF NOT EXISTS entity_class_index
+        ON entity (class);
+        ",
+    )?;
+
+    let mut stmt = tx.prepare("SELECT 1 FROM pragma_table_info(?) WHERE name=?")?;
+    if !stmt.exists(["entity", "dps"])? {
+        tx.execute("ALTER TABLE entity ADD COLUMN dps INTEGER", [])?;
+    }
+    if !stmt.exists(["entity", "character_id"])? {
+        tx.execute("ALTER TABLE entity ADD COLUMN character_id INTEGER", [])?;
+    }
+    if !stmt.exists(["entity", "engravings"])? {
+        tx.execute("ALTER TABLE entity ADD COLUMN engravings TEXT", [])?;
+    }
+    if !stmt.exists(["entity", "gear_hash"])? {
+        tx.execute("ALTER TABLE entity ADD COLUMN gear_hash TEXT", [])?;
+    }
+    tx.execute("UPDATE entity SET dps = coalesce(json_extract(damage_stats, '$.dps'), 0) WHERE dps IS NULL;", [])?;
+    stmt.finalize()
+}
+
+fn migration_full_text_search(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    tx.execute_batch(
+        "
+        CREATE TABLE encounter_preview (
+            id INTEGER PRIMARY KEY,
+            fight_start INTEGER,
+            current_boss TEXT,
+            duration INTEGER,
+            players TEXT,
+            difficulty TEXT,
+            local_player TEXT,
+            my_dps INTEGER,
+            favorite BOOLEAN NOT NULL DEFAULT 0,
+            cleared BOOLEAN,
+            boss_only_damage BOOLEAN NOT NULL DEFAULT 0,
+            FOREIGN KEY (id) REFERENCES encounter(id) ON DELETE CASCADE
+        );
+
+        INSERT INTO encounter_preview SELECT
+            id, fight_start, current_boss, duration, 
+            (
+                SELECT GROUP_CONCAT(class_id || ':' || name ORDER BY dps DESC)
+                FROM entity
+                WHERE encounter_id = encounter.id AND entity_type = 'PLAYER'
+            ) AS players,
+            difficulty, local_player,
+            (
+                SELECT dps
+                FROM entity
+                WHERE encounter_id = encounter.id AND name = encounter.local_player
+            ) AS my_dps,
+            favorite, cleared, boss_only_damage
+        FROM encounter;
+
+        DROP INDEX IF EXISTS encounter_fight_start_index;
+        DROP INDEX IF EXISTS encounter_current_boss_index;
+        DROP INDEX IF EXISTS encounter_favorite_index;
+        DROP INDEX IF EXISTS entity_name_index;
+        DROP INDEX IF EXISTS entity_class_index;
+
+        ALTER TABLE encounter DROP COLUMN fight_start;
+        ALTER TABLE encounter DROP COLUMN current_boss;
+        ALTER TABLE encounter DROP COLUMN duration;
+        ALTER TABLE encounter DROP COLUMN difficulty;
+        ALTER TABLE encounter DROP COLUMN local_player;
+        ALTER TABLE encounter DROP COLUMN favorite;
+        ALTER TABLE encounter DROP COLUMN cleared;
+        ALTER TABLE encounter DROP COLUMN boss_only_damage;
+
+        ALTER TABLE encounter ADD COLUMN boss_hp_log BLOB;
+        ALTER TABLE encounter ADD COLUMN stagger_log TEXT;
+
+        CREATE INDEX encounter_preview_favorite_index ON encounter_preview(favorite);
+        CREATE INDEX encounter_preview_fight_start_index ON encounter_preview(fight_start);
+        CREATE INDEX encounter_preview_my_dps_index ON encounter_preview(my_dps);
+        CREATE INDEX encounter_preview_duration_index ON encounter_preview(duration);
+
+        CREATE VIRTUAL TABLE encounter_search USING fts5(
+            current_boss, players, columnsize=0, detail=full,
+            tokenize='trigram remove_diacritics 1',
+            content=encounter_preview, content_rowid=id
+        );
+        INSERT INTO encounter_search(encounter_search) VALUES('rebuild');
+        CREATE TRIGGER encounter_preview_ai AFTER INSERT ON encounter_preview BEGIN
+            INSERT INTO encounter_search(rowid, current_boss, players)
+            VALUES (new.id, new.current_boss, new.players);
+        END;
+        CREATE TRIGGER encounter_preview_ad AFTER DELETE ON encounter_preview BEGIN
+            INSERT INTO encounter_search(encounter_search, rowid, current_boss, players)
+            VALUES('delete', old.id, old.current_boss, old.players);
+        END;
+        CREATE TRIGGER encounter_preview_au AFTER UPDATE OF current_boss, players ON encounter_preview BEGIN
+            INSERT INTO encounter_search(encounter_search, rowid, current_boss, players)
+            VALUES('delete', old.id, old.current_boss, old.players);
+            INSERT INTO encounter_search(rowid, current_boss, players)
+            VALUES (new.id, new.current_boss, new.players);
+        END;
+        ",
+    )
+}
+
+fn migration_sync(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS sync_logs (
+        encounter_id INTEGER PRIMARY KEY,
+        upstream_id TEXT,
+        failed BOOLEAN NOT NULL DEFAULT 0,
+        FOREIGN KEY (encounter_id) REFERENCES encounter (id) ON DELETE CASCADE
+    );",
+    )
+}
+
+// legacy, ran on every start before versioning so the columns may already be there
+fn migration_specs(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    let mut stmt = tx.prepare("SELECT 1 FROM pragma_table_info(?) WHERE name=?")?;
+    if !stmt.exists(["entity", "spec"])? {
+        info!("adding spec info columns");
+        tx.execute_batch(
+            "
+                ALTER TABLE entity ADD COLUMN spec TEXT;
+                ALTER TABLE entity ADD COLUMN ark_passive_active BOOLEAN;
+                ALTER TABLE entity ADD COLUMN ark_passive_data TEXT;
+                ",
+        )?;
+    }
+
+    stmt.finalize()
+}
+
+fn migration_entity_key(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    // entities used to be keyed by display name, rebuild the table with the same keys the
+    // parser uses. old rows never stored object ids, so the rowid stands in for one, both
+    // when no stable id is known and when two names share a stable id in one encounter
+    info!("keying entities by id");
+    tx.execute_batch(
+        "
+        CREATE TABLE entity_new (
+            name TEXT,
+            character_id INTEGER,
+            encounter_id INTEGER NOT NULL,
+            npc_id INTEGER,
+            entity_type TEXT,
+            class_id INTEGER,
+            class TEXT,
+            gear_score REAL,
+            current_hp INTEGER,
+            max_hp INTEGER,
+            is_dead INTEGER,
+            skills TEXT,
+            damage_stats TEXT,
+            dps INTEGER,
+            skill_stats TEXT,
+            last_update INTEGER,
+            engravings TEXT,
+            gear_hash TEXT,
+            spec TEXT,
+            ark_passive_active BOOLEAN,
+            ark_passive_data TEXT,
+            entity_key TEXT NOT NULL,
+            PRIMARY KEY (encounter_id, entity_key),
+            FOREIGN KEY (encounter_id) REFERENCES encounter (id) ON DELETE CASCADE
+        );
+
+        INSERT INTO entity_new SELECT
+            name, character_id, encounter_id, npc_id, entity_type, class_id, class,
+            gear_score, current_hp, max_hp, is_dead, skills, damage_stats, dps,
+            skill_stats, last_update, engravings, gear_hash, spec, ark_passive_active,
+            ark_passive_data,
+            CASE
+                WHEN ROW_NUMBER() OVER (
+                    PARTITION BY encounter_id, stable_key ORDER BY max_hp DESC, dps DESC
+                ) = 1 THEN stable_key
+                ELSE printf('obj:%x', row_id)
+            END
+        FROM (
+            SELECT *, rowid AS row_id,
+                CASE
+                    WHEN entity_type = 'PLAYER' AND character_id > 0
+                        THEN 'pc:' || character_id
+                    WHEN entity_type = 'BOSS' AND npc_id > 0 THEN 'npc:' || npc_id
+                    ELSE printf('obj:%x', rowid)
+                END AS stable_key
+            FROM entity
+        );
+
+        DROP TABLE entity;
+        ALTER TABLE entity_new RENAME TO entity;
+
+        CREATE INDEX IF NOT EXISTS entity_encounter_id_index
+        ON entity (encounter_id desc);
+        ",
+    )
+}
+
+fn migration_raid_gates(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    info!("adding raid gate columns");
+    tx.execute_batch(
+        "
+        ALTER TABLE encounter_preview ADD COLUMN raid TEXT;
+        ALTER TABLE encounter_preview ADD COLUMN gate INTEGER;
+        CREATE INDEX encounter_preview_raid_index ON encounter_preview(raid, gate);
+        ",
+    )?;
+
+    // backfill from the npc ids of saved bosses, the other bosses of an encounter are
+    // needed to tell apart gates that share a boss name
+    let mut boss_stmt = tx.prepare(
+        "
+        SELECT encounter_id, npc_id, name
+        FROM entity
+        WHERE entity_type = 'BOSS'
+        ORDER BY encounter_id, max_hp DESC
+        ",
+    )?;
+    let mut rows = boss_stmt.query([])?;
+    let mut bosses_by_encounter: BTreeMap<i64, Vec<(u32, String)>> = BTreeMap::new();
+    while let Some(row) = rows.next()? {
+        bosses_by_encounter.entry(row.get(0)?).or_default().push((
+            row.get::<_, Option<u32>>(1)?.unwrap_or_default(),
+            row.get(2)?,
+        ));
+    }
+    let mut update_stmt =
+        tx.prepare("UPDATE encounter_preview SET raid = ?, gate = ? WHERE id = ?")?;
+    for (encounter_id, bosses) in bosses_by_encounter {
+        let boss_names: Vec<&str> = bosses.iter().map(|(_, name)| name.as_str()).collect();
+        // the boss with the most hp is taken as the encounter boss
+        if let Some(raid_gate) = bosses
+            .iter()
+            .find_map(|(npc_id, _)| RaidGate::for_boss(*npc_id, &boss_names))
+        {
+            update_stmt.execute(params![raid_gate.raid, raid_gate.gate, encounter_id])?;
+        }
+    }
+
+    Ok(())
+}
+
+fn migration_personal_bests(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    // records outlive the encounter they came from, so there is no cascade on delete
+    // the backfill reads main.entity alone: nothing can be archived before the archive
+    // migration further down, and restores replace the archive along with the db
+    info!("adding personal best table");
+    tx.execute_batch(
+        "
+        CREATE TABLE personal_best (
+            name TEXT NOT NULL,
+            class_id INTEGER NOT NULL,
+            class TEXT,
+            boss TEXT NOT NULL,
+            difficulty TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            value INTEGER NOT NULL,
+            skill_id INTEGER,
+            encounter_id INTEGER NOT NULL,
+            fight_start INTEGER NOT NULL,
+            PRIMARY KEY (name, class_id, boss, difficulty, kind)
+        );
+
+        INSERT INTO personal_best (name, class_id, class, boss, difficulty, kind, value, encounter_id, fight_start)
+        SELECT en.name, en.class_id, en.class, ep.current_boss, ep.difficulty, 'dps', MAX(en.dps), ep.id, ep.fight_start
+        FROM encounter_preview ep
+        JOIN entity en ON en.encounter_id = ep.id AND en.name = ep.local_player
+        WHERE ep.difficulty IS NOT NULL AND ep.difficulty != '' AND en.class_id > 0
+        GROUP BY en.name, en.class_id, ep.current_boss, ep.difficulty;
+
+        INSERT INTO personal_best (name, class_id, class, boss, difficulty, kind, value, encounter_id, fight_start)
+        SELECT en.name, en.class_id, en.class, ep.current_boss, ep.difficulty, 'clear_time', MIN(ep.duration), ep.id, ep.fight_start
+        FROM encounter_preview ep
+        JOIN entity en ON en.encounter_id = ep.id AND en.name = ep.local_player
+        WHERE ep.cleared AND ep.difficulty IS NOT NULL AND ep.difficulty != '' AND en.class_id > 0
+        GROUP BY en.name, en.class_id, ep.current_boss, ep.difficulty;
+        ",
+    )
+}
+
+fn migration_raid_sessions(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    info!("adding raid session column");
+    tx.execute_batch(
+        "
+        ALTER TABLE encounter_preview ADD COLUMN session_id INTEGER;
+        CREATE INDEX encounter_preview_session_index ON encounter_preview(session_id);
+        ",
+    )?;
+    session_assign::backfill_sessions(tx)
+}
+
+fn migration_trash(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    info!("adding trash column");
+    tx.execute_batch(
+        "
+        ALTER TABLE encounter_preview ADD COLUMN deleted_at INTEGER;
+        CREATE INDEX encounter_preview_deleted_at_index ON encounter_preview(deleted_at);
+        ",
+    )
+}
+
+fn migration_archive(tx: &Transaction) -> Result<(), rusqlite::Error> {
+    info!("adding archive column");
+    tx.execute(
+        "ALTER TABLE encounter_preview ADD COLUMN archived BOOLEAN NOT NULL DEFAULT 0",
+        [],
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn backup_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("migrations-{}-{}", name, std::process::id()))
+    }
+
+    // a db as it was before the given version, for checking a single migration on old data
+    fn migrate_until(conn: &mut Connection, version: i32) {
+        for migration in MIGRATIONS.iter().take_while(|m| m.version < version) {
+            let tx = conn.transaction().unwrap();
+            (migration.run)(&tx).unwrap();
+            tx.pragma_update(None, "user_version", migration.version)
+                .unwrap();
+            tx.commit().unwrap();
+        }
+    }
+
+    fn latest_version() -> i32 {
+        MIGRATIONS.last().unwrap().version
+    }
+
+    #[test]
+    fn versions_are_sequential() {
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, index as i32 + 1, "{}", migration.name);
+        }
+    }
+
+    #[test]
+    fn new_db_runs_every_migration_once() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let dir = backup_dir("new");
+
+        let applied = run_migrations(&mut conn, &dir, false).unwrap();
+        assert_eq!(applied.len(), MIGRATIONS.len());
+        assert_eq!(get_schema_version(&conn).unwrap(), latest_version());
+
+        assert!(run_migrations(&mut conn, &dir, false).unwrap().is_empty());
+        // nothing to back up in a new db
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn dry_run_leaves_the_db_alone() {
+        let mut conn = Connection::open_in_memory().unwrap();
+
+        let pending = run_migrations(&mut conn, &backup_dir("dry-run"), true).unwrap();
+        assert_eq!(pending.len(), MIGRATIONS.len());
+        assert_eq!(get_schema_version(&conn).unwrap(), 0);
+        assert!(!table_exists(&conn, "encounter").unwrap());
+    }
+
+    #[test]
+    fn unversioned_db_goes_through_the_legacy_chain() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        // everything before entity keys shipped without user_version
+        migrate_until(&mut conn, 5);
+        conn.pragma_update(None, "user_version", 0).unwrap();
+
+        run_migrations(&mut conn, &backup_dir("legacy"), false).unwrap();
+        assert_eq!(get_schema_version(&conn).unwrap(), latest_version());
+        fs::remove_dir_all(backup_dir("legacy")).ok();
+    }
+
+    #[test]
+    fn entity_key_keeps_colliding_rows() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate_until(&mut conn, 5);
+        conn.execute_batch(
+            "
+            INSERT INTO encounter (id) VALUES (1);
+            INSERT INTO encounter_preview (id, current_boss) VALUES (1, 'Boss');
+            INSERT INTO entity (name, encounter_id, entity_type, npc_id, character_id, max_hp, dps)
+            VALUES
+                ('Player', 1, 'PLAYER', 0, 42, 100, 10),
+                ('Boss', 1, 'BOSS', 480010, 0, 1000, 0),
+                ('Boss Clone', 1, 'BOSS', 480010, 0, 500, 0),
+                ('Add', 1, 'MONSTER', 7, 0, 10, 0),
+                ('Add Two', 1, 'MONSTER', 7, 0, 10, 0);
+            ",
+        )
+        .unwrap();
+
+        let dir = backup_dir("entity-key");
+        run_migrations(&mut conn, &dir, false).unwrap();
+        // rebuilding the entity table is destructive
+        assert!(dir.exists());
+        fs::remove_dir_all(&dir).ok();
+
+        let keys = conn
+            .prepare("SELECT name, entity_key FROM entity ORDER BY name")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<Vec<(String, String)>, _>>()
+            .unwrap();
+        assert_eq!(keys.len(), 5);
+        assert!(keys.contains(&("Player".to_string(), "pc:42".to_string())));
+        assert!(keys.contains(&("Boss".to_string(), "npc:480010".to_string())));
+        assert!(keys
+            .iter()
+            .filter(|(name, _)| name != "Player" && name != "Boss")
+            .all(|(_, key)| key.starts_with("obj:")));
+    }
+}
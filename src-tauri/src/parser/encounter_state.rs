@@ -1,24 +1,27 @@
 use chrono::Utc;
 use hashbrown::HashMap;
-use log::{info, warn};
+use log::{error, info, warn};
 use meter_core::packets::definitions::PKTIdentityGaugeChangeNotify;
 use moka::sync::Cache;
 use rsntp::SntpClient;
-use rusqlite::Connection;
-use std::cmp::{max, Ordering};
+use std::cmp::{max, min, Ordering};
 use std::default::Default;
 
+use crate::db::Database;
+use crate::error::AppError;
 use crate::parser::debug_print;
 use tauri::{Manager, Window, Wry};
 use tokio::task;
 
 use crate::parser::entity_tracker::{Entity, EntityTracker};
+use crate::parser::hp_forecast::forecast_boss_hp;
 use crate::parser::models::*;
 use crate::parser::rdps::*;
 use crate::parser::skill_tracker::SkillTracker;
 use crate::parser::stats_api::{PlayerStats, StatsApi};
 use crate::parser::status_tracker::StatusEffectDetails;
 use crate::parser::utils::*;
+use crate::parser::wipe_report::WipeTracker;
 
 const RDPS_VALID_LIMIT: i64 = 25_000;
 
@@ -59,6 +62,9 @@ pub struct EncounterState {
     custom_id_map: HashMap<u32, u32>,
 
     pub damage_is_valid: bool,
+
+    wipe_tracker: WipeTracker,
+    wipe_report: Option<WipeReport>,
 }
 
 impl EncounterState {
@@ -96,6 +102,9 @@ impl EncounterState {
             custom_id_map: HashMap::new(),
 
             damage_is_valid: true,
+
+            wipe_tracker: WipeTracker::new(),
+            wipe_report: None,
         }
     }
 
@@ -106,6 +115,7 @@ impl EncounterState {
         self.encounter.fight_start = 0;
         self.encounter.boss_only_damage = self.boss_only_damage;
         self.encounter.entities = HashMap::new();
+        self.encounter.entity_names = HashMap::new();
         self.encounter.current_boss_name = "".to_string();
         self.encounter.encounter_damage_stats = Default::default();
         self.prev_stagger = 0;
@@ -126,12 +136,15 @@ impl EncounterState {
         self.skill_tracker = SkillTracker::new();
 
         self.custom_id_map = HashMap::new();
-        
+
+        self.wipe_tracker.reset();
+        self.wipe_report = None;
+
         for (key, entity) in clone.entities.into_iter().filter(|(_, e)| {
             e.entity_type == EntityType::PLAYER
                 || (keep_bosses && e.entity_type == EntityType::BOSS)
         }) {
-            self.encounter.entities.insert(
+            self.encounter.insert_entity(
                 key,
                 EncounterEntity {
                     name: entity.name,
@@ -151,16 +164,65 @@ impl EncounterState {
         }
     }
 
+    pub fn boss_hp_forecast(&self) -> Option<BossHpForecast> {
+        if self.encounter.fight_start == 0 || self.encounter.current_boss_name.is_empty() {
+            return None;
+        }
+
+        let boss = self
+            .encounter
+            .entity_by_name(&self.encounter.current_boss_name)?;
+        if boss.is_dead {
+            return None;
+        }
+        let log = self.boss_hp_log.get(&boss.name)?;
+        let elapsed = (self.encounter.last_combat_packet - self.encounter.fight_start) / 1000;
+        let enrage = self
+            .raid_gate()
+            .and_then(|raid_gate| raid_gate.enrage(&self.raid_difficulty));
+
+        forecast_boss_hp(boss, log, elapsed, enrage)
+    }
+
+    fn raid_gate(&self) -> Option<&'static RaidGate> {
+        let boss_names: Vec<&str> = self
+            .encounter
+            .entities
+            .values()
+            .filter(|e| e.entity_type == EntityType::BOSS)
+            .map(|e| e.name.as_str())
+            .collect();
+        self.encounter
+            .entity_by_name(&self.encounter.current_boss_name)
+            .and_then(|boss| RaidGate::for_boss(boss.npc_id, &boss_names))
+    }
+
+    fn build_wipe_report(&self) -> WipeReport {
+        // the stagger bar is only known while stagger packets are tracked
+        let max_stagger = self.encounter.encounter_damage_stats.max_stagger;
+        let stagger_check_missed = if max_stagger > 0 {
+            Some(self.prev_stagger > 0 && self.prev_stagger < max_stagger)
+        } else {
+            None
+        };
+
+        self.wipe_tracker.build_report(
+            &self.encounter,
+            &self.boss_hp_log,
+            stagger_check_missed,
+            Utc::now().timestamp_millis(),
+        )
+    }
+
     // update local player as we get more info
     pub fn update_local_player(&mut self, entity: &Entity) {
         // we replace the existing local player if it exists, since its name might have changed (from hex or "You" to character name)
-        if let Some(mut local) = self.encounter.entities.remove(&self.encounter.local_player) {
+        let local_player = self.encounter.local_player.clone();
+        if let Some(mut local) = self.encounter.remove_entity_by_name(&local_player) {
             // update local player name, insert back into encounter
             self.encounter.local_player.clone_from(&entity.name);
             update_player_entity(&mut local, entity);
-            self.encounter
-                .entities
-                .insert(self.encounter.local_player.clone(), local);
+            self.encounter.insert_entity(get_entity_key(entity), local);
         } else {
             // cannot find old local player by name, so we look by local player's entity id
             // this can happen when the user started meter late
@@ -172,14 +234,12 @@ impl EncounterState {
                 .map(|(key, _)| key.clone());
 
             // if we find the old local player, we update its name and insert back into encounter
-            if let Some(old_local) = old_local {
-                let mut new_local = self.encounter.entities[&old_local].clone();
+            if let Some(mut new_local) =
+                old_local.and_then(|old_local| self.encounter.entities.remove(&old_local))
+            {
                 update_player_entity(&mut new_local, entity);
-                self.encounter.entities.remove(&old_local);
                 self.encounter.local_player.clone_from(&entity.name);
-                self.encounter
-                    .entities
-                    .insert(self.encounter.local_player.clone(), new_local);
+                self.encounter.insert_entity(get_entity_key(entity), new_local);
             }
         }
     }
@@ -191,15 +251,13 @@ impl EncounterState {
         }
 
         // replace or insert local player
-        if let Some(mut local_player) = self.encounter.entities.remove(&self.encounter.local_player)
-        {
+        let local_player = self.encounter.local_player.clone();
+        if let Some(mut local_player) = self.encounter.remove_entity_by_name(&local_player) {
             update_player_entity(&mut local_player, &entity);
-            self.encounter
-                .entities
-                .insert(entity.name.clone(), local_player);
+            self.encounter.insert_entity(get_entity_key(&entity), local_player);
         } else {
-            let entity = encounter_entity_from_entity(&entity);
-            self.encounter.entities.insert(entity.name.clone(), entity);
+            self.encounter
+                .insert_entity(get_entity_key(&entity), encounter_entity_from_entity(&entity));
         }
         self.encounter.local_player = entity.name;
 
@@ -227,6 +285,13 @@ impl EncounterState {
                     if phase_code == 0 {
                         stats_api.valid_zone = false;
                     }
+                    if phase_code == 4 && self.encounter.fight_start > 0 {
+                        let wipe_report = self.build_wipe_report();
+                        if let Err(e) = self.window.emit("wipe-report", &wipe_report) {
+                            error!("failed to emit wipe-report: {}", e);
+                        }
+                        self.wipe_report = Some(wipe_report);
+                    }
                     self.save_to_db(stats_api, false);
                     self.saved = true;
                 }
@@ -238,19 +303,21 @@ impl EncounterState {
 
     // replace local player
     pub fn on_init_pc(&mut self, entity: Entity, hp: i64, max_hp: i64) {
-        self.encounter.entities.remove(&self.encounter.local_player);
+        let local_player = self.encounter.local_player.clone();
+        self.encounter.remove_entity_by_name(&local_player);
         self.encounter.local_player.clone_from(&entity.name);
         let mut player = encounter_entity_from_entity(&entity);
         player.current_hp = hp;
         player.max_hp = max_hp;
-        self.encounter.entities.insert(player.name.clone(), player);
+        self.encounter.insert_entity(get_entity_key(&entity), player);
     }
 
     // add or update player to encounter
     pub fn on_new_pc(&mut self, entity: Entity, hp: i64, max_hp: i64) {
+        let key = resolve_entity_key(&mut self.encounter, &entity);
         self.encounter
             .entities
-            .entry(entity.name.clone())
+            .entry(key.clone())
             .and_modify(|player| {
                 player.id = entity.id;
                 player.gear_score = entity.gear_level;
@@ -266,15 +333,17 @@ impl EncounterState {
                 player.max_hp = max_hp;
                 player
             });
+        self.encounter.entity_names.insert(entity.name, key);
     }
 
     // add or update npc to encounter
     // we set current boss if npc matches criteria
     pub fn on_new_npc(&mut self, entity: Entity, hp: i64, max_hp: i64) {
         let entity_name = entity.name.clone();
+        let key = resolve_entity_key(&mut self.encounter, &entity);
         self.encounter
             .entities
-            .entry(entity_name.clone())
+            .entry(key.clone())
             .and_modify(|e| {
                 if entity.entity_type != EntityType::BOSS && e.entity_type != EntityType::BOSS {
                     e.npc_id = entity.npc_id;
@@ -296,30 +365,39 @@ impl EncounterState {
                 npc.max_hp = max_hp;
                 npc
             });
+        self.encounter.entity_names.insert(entity_name.clone(), key.clone());
 
-        if let Some(npc) = self.encounter.entities.get(&entity_name) {
+        if let Some(npc) = self.encounter.entities.get(&key) {
             if npc.entity_type == EntityType::BOSS {
                 // if current encounter has no boss, we set the boss
                 // if current encounter has a boss, we check if new boss has more max hp, or if current boss is dead
-                self.encounter.current_boss_name = if self
+                // known raid bosses take priority over other boss grade npcs
+                let is_new_boss = self
                     .encounter
-                    .entities
-                    .get(&self.encounter.current_boss_name)
-                    .map_or(true, |boss| npc.max_hp >= boss.max_hp || boss.is_dead)
-                {
-                    entity_name
-                } else {
-                    self.encounter.current_boss_name.clone()
-                };
+                    .entity_by_name(&self.encounter.current_boss_name)
+                    .map_or(true, |boss| {
+                        match (
+                            RAID_BOSS_DATA.contains_key(&npc.npc_id),
+                            RAID_BOSS_DATA.contains_key(&boss.npc_id),
+                        ) {
+                            (true, false) => true,
+                            (false, true) => boss.is_dead,
+                            _ => npc.max_hp >= boss.max_hp || boss.is_dead,
+                        }
+                    });
+                if is_new_boss {
+                    self.encounter.current_boss_name = entity_name;
+                }
             }
         }
     }
 
     pub fn on_death(&mut self, dead_entity: &Entity) {
+        let key = resolve_entity_key(&mut self.encounter, dead_entity);
         let entity = self
             .encounter
             .entities
-            .entry(dead_entity.name.clone())
+            .entry(key)
             .or_insert_with(|| encounter_entity_from_entity(dead_entity));
 
         if (dead_entity.entity_type != EntityType::PLAYER
@@ -342,6 +420,15 @@ impl EncounterState {
         entity.is_dead = true;
         entity.damage_stats.deaths += 1;
         entity.damage_stats.death_time = Utc::now().timestamp_millis();
+
+        if entity.entity_type == EntityType::PLAYER && self.encounter.fight_start > 0 {
+            self.wipe_tracker.on_death(
+                entity.id,
+                &entity.name,
+                entity.class_id,
+                entity.damage_stats.death_time,
+            );
+        }
     }
 
     pub fn on_skill_start(
@@ -356,12 +443,16 @@ impl EncounterState {
         if self.encounter.fight_start == 0 {
             return (0, None);
         }
+        if source_entity.entity_type == EntityType::BOSS {
+            self.wipe_tracker.on_boss_skill(skill_id, timestamp);
+        }
         let skill_name = get_skill_name(&skill_id);
         let mut tripod_change = false;
+        let key = resolve_entity_key(&mut self.encounter, source_entity);
         let entity = self
             .encounter
             .entities
-            .entry(source_entity.name.clone())
+            .entry(key)
             .or_insert_with(|| {
                 let (skill_name, skill_icon, summons) = get_skill_name_and_icon(
                     &skill_id,
@@ -562,12 +653,9 @@ impl EncounterState {
         if hit_flag == HitFlag::INVINCIBLE {
             return;
         }
-        if hit_flag == HitFlag::DAMAGE_SHARE
+        let is_unattributed_share = hit_flag == HitFlag::DAMAGE_SHARE
             && damage_data.skill_id == 0
-            && damage_data.skill_effect_id == 0
-        {
-            return;
-        }
+            && damage_data.skill_effect_id == 0;
 
         let mut skill_effect_id = damage_data.skill_effect_id;
         if proj_entity.entity_type == EntityType::PROJECTILE
@@ -576,17 +664,19 @@ impl EncounterState {
             skill_effect_id = proj_entity.skill_effect_id;
         }
 
+        let source_key = resolve_entity_key(&mut self.encounter, dmg_src_entity);
         let mut source_entity = self
             .encounter
             .entities
-            .entry(dmg_src_entity.name.clone())
+            .entry(source_key.clone())
             .or_insert_with(|| encounter_entity_from_entity(dmg_src_entity))
             .to_owned();
 
+        let target_key = resolve_entity_key(&mut self.encounter, dmg_target_entity);
         let mut target_entity = self
             .encounter
             .entities
-            .entry(dmg_target_entity.name.clone())
+            .entry(target_key.clone())
             .or_insert_with(|| {
                 let mut target_entity = encounter_entity_from_entity(dmg_target_entity);
                 target_entity.current_hp = damage_data.target_current_hp;
@@ -607,6 +697,16 @@ impl EncounterState {
             return;
         }
 
+        // shared damage with no source skill is tracked on its own and kept out of damage dealt
+        if is_unattributed_share {
+            if self.encounter.fight_start != 0 {
+                source_entity.damage_stats.damage_share_damage += damage_data.damage;
+                source_entity.skill_stats.damage_shares += 1;
+                self.encounter.insert_entity(source_key, source_entity);
+            }
+            return;
+        }
+
         if self.encounter.fight_start == 0 {
             self.encounter.fight_start = timestamp;
             self.skill_tracker.fight_start = timestamp;
@@ -641,8 +741,10 @@ impl EncounterState {
         }
 
         let mut damage = damage_data.damage;
+        let mut overkill = 0;
         if target_entity.entity_type != EntityType::PLAYER && damage_data.target_current_hp < 0 {
-            damage += damage_data.target_current_hp;
+            overkill = min(-damage_data.target_current_hp, damage);
+            damage -= overkill;
         }
 
         let mut skill_id = if damage_data.skill_id != 0 {
@@ -731,6 +833,35 @@ impl EncounterState {
         source_entity.skill_stats.hits += 1;
         skill.hits += 1;
 
+        if overkill > 0 {
+            skill.overkill_damage += overkill;
+            source_entity.damage_stats.overkill_damage += overkill;
+        }
+
+        match hit_flag {
+            HitFlag::MISS => {
+                source_entity.skill_stats.misses += 1;
+                skill.misses += 1;
+            }
+            HitFlag::DODGE | HitFlag::DODGE_HIT => {
+                source_entity.skill_stats.dodges += 1;
+                skill.dodges += 1;
+            }
+            HitFlag::IMMUNE | HitFlag::IMMUNE_SILENCED => {
+                source_entity.skill_stats.immunes += 1;
+                skill.immunes += 1;
+            }
+            HitFlag::REFLECT => {
+                source_entity.skill_stats.reflects += 1;
+                skill.reflects += 1;
+            }
+            HitFlag::DAMAGE_SHARE => {
+                source_entity.skill_stats.damage_shares += 1;
+                skill.damage_shares += 1;
+            }
+            _ => {}
+        }
+
         if hit_flag == HitFlag::CRITICAL || hit_flag == HitFlag::DOT_CRITICAL {
             source_entity.skill_stats.crits += 1;
             source_entity.damage_stats.crit_damage += damage;
@@ -752,6 +883,55 @@ impl EncounterState {
             skill.front_attack_damage += damage;
             skill_hit.front_attack = true;
         }
+        if hit_option == HitOption::FLANK_ATTACK {
+            source_entity.skill_stats.flank_attacks += 1;
+            source_entity.damage_stats.flank_attack_damage += damage;
+            skill.flank_attacks += 1;
+            skill.flank_attack_damage += damage;
+            skill_hit.flank_attack = true;
+        }
+
+        let damage_attribute = damage_data.damage_attribute.unwrap_or_default();
+        skill
+            .damage_by_attribute
+            .entry(damage_attribute)
+            .and_modify(|e| *e += damage)
+            .or_insert(damage);
+        source_entity
+            .damage_stats
+            .damage_by_attribute
+            .entry(damage_attribute)
+            .and_modify(|e| *e += damage)
+            .or_insert(damage);
+        skill
+            .damage_by_type
+            .entry(damage_data.damage_type)
+            .and_modify(|e| *e += damage)
+            .or_insert(damage);
+        source_entity
+            .damage_stats
+            .damage_by_type
+            .entry(damage_data.damage_type)
+            .and_modify(|e| *e += damage)
+            .or_insert(damage);
+
+        if target_entity.entity_type != EntityType::PLAYER {
+            let target_npc_id = dmg_target_entity.npc_id;
+            let skill_target = skill.damage_by_target.entry(target_npc_id).or_default();
+            skill_target.damage += damage;
+            skill_target.hits += 1;
+            let entity_target = source_entity
+                .damage_stats
+                .damage_by_target
+                .entry(target_npc_id)
+                .or_default();
+            entity_target.damage += damage;
+            entity_target.hits += 1;
+            if skill_hit.crit {
+                skill_target.crits += 1;
+                entity_target.crits += 1;
+            }
+        }
 
         if source_entity.entity_type == EntityType::PLAYER {
             self.encounter.encounter_damage_stats.total_damage_dealt += damage;
@@ -900,6 +1080,14 @@ impl EncounterState {
                 skill.buffed_by_hat += damage;
                 source_entity.damage_stats.buffed_by_hat += damage;
             }
+            self.wipe_tracker.on_player_hit(
+                source_entity.id,
+                timestamp,
+                damage,
+                is_buffed_by_support,
+                is_buffed_by_identity,
+                is_debuffed_by_support,
+            );
 
             let stabilized_status_active =
                 (source_entity.current_hp as f64 / source_entity.max_hp as f64) > 0.65;
@@ -969,7 +1157,7 @@ impl EncounterState {
                                 None => continue,
                             };
                         let caster_encounter_entity =
-                            match self.encounter.entities.get(&caster_entity.name) {
+                            match find_encounter_entity(&self.encounter, caster_entity) {
                                 Some(entity) => entity,
                                 None => continue,
                             };
@@ -1167,7 +1355,7 @@ impl EncounterState {
                                 None => continue,
                             };
                         let caster_encounter_entity =
-                            match self.encounter.entities.get(&caster_entity.name) {
+                            match find_encounter_entity(&self.encounter, caster_entity) {
                                 Some(entity) => entity,
                                 None => continue,
                             };
@@ -1446,7 +1634,7 @@ impl EncounterState {
                         let delta = crit.rate * crit_gain_unit;
                         apply_rdps(
                             &mut source_entity,
-                            self.encounter.entities.get_mut(&crit.caster),
+                            self.encounter.entity_by_name_mut(&crit.caster),
                             skill_id,
                             delta,
                             &mut skill_hit,
@@ -1458,7 +1646,7 @@ impl EncounterState {
                             * unit_rate;
                         apply_rdps(
                             &mut source_entity,
-                            self.encounter.entities.get_mut(&dmg.caster),
+                            self.encounter.entity_by_name_mut(&dmg.caster),
                             skill_id,
                             delta,
                             &mut skill_hit,
@@ -1470,7 +1658,7 @@ impl EncounterState {
                             (dmg.rate / (1.0 + rdps_data.skill_dmg_rate.self_sum_rate)) * unit_rate;
                         apply_rdps(
                             &mut source_entity,
-                            self.encounter.entities.get_mut(&dmg.caster),
+                            self.encounter.entity_by_name_mut(&dmg.caster),
                             skill_id,
                             delta,
                             &mut skill_hit,
@@ -1483,7 +1671,7 @@ impl EncounterState {
                         let delta = dmg.rate * mult_gain_unit;
                         apply_rdps(
                             &mut source_entity,
-                            self.encounter.entities.get_mut(&dmg.caster),
+                            self.encounter.entity_by_name_mut(&dmg.caster),
                             skill_id,
                             delta,
                             &mut skill_hit,
@@ -1497,7 +1685,7 @@ impl EncounterState {
                         let delta = dmg.rate * atk_pow_sub_rate_1_gain_unit;
                         apply_rdps(
                             &mut source_entity,
-                            self.encounter.entities.get_mut(&dmg.caster),
+                            self.encounter.entity_by_name_mut(&dmg.caster),
                             skill_id,
                             delta,
                             &mut skill_hit,
@@ -1509,8 +1697,7 @@ impl EncounterState {
                         apply_rdps(
                             &mut source_entity,
                             self.encounter
-                                .entities
-                                .get_mut(&attack_power_amplify.caster),
+                                .entity_by_name_mut(&attack_power_amplify.caster),
                             skill_id,
                             delta,
                             &mut skill_hit,
@@ -1542,12 +1729,26 @@ impl EncounterState {
                 self.encounter.encounter_damage_stats.top_damage_taken,
                 target_entity.damage_stats.damage_taken,
             );
+            if source_entity.entity_type != EntityType::PLAYER {
+                self.wipe_tracker.on_hit_taken(
+                    target_entity.id,
+                    source_entity.id,
+                    skill_id,
+                    timestamp,
+                );
+            }
         }
         // update current_boss
         else if target_entity.entity_type == EntityType::BOSS {
-            self.encounter
-                .current_boss_name
-                .clone_from(&target_entity.name);
+            let is_raid_boss_current = self
+                .encounter
+                .entity_by_name(&self.encounter.current_boss_name)
+                .is_some_and(|boss| RAID_BOSS_DATA.contains_key(&boss.npc_id));
+            if !is_raid_boss_current || RAID_BOSS_DATA.contains_key(&dmg_target_entity.npc_id) {
+                self.encounter
+                    .current_boss_name
+                    .clone_from(&target_entity.name);
+            }
             target_entity.id = dmg_target_entity.id;
             target_entity.npc_id = dmg_target_entity.npc_id;
 
@@ -1588,19 +1789,16 @@ impl EncounterState {
             );
         }
 
-        self.encounter
-            .entities
-            .insert(source_entity.name.clone(), source_entity);
-        self.encounter
-            .entities
-            .insert(target_entity.name.clone(), target_entity);
+        self.encounter.insert_entity(source_key, source_entity);
+        self.encounter.insert_entity(target_key, target_entity);
     }
 
-    pub fn on_counterattack(&mut self, source_entity: &Entity) {
+    pub fn on_counterattack(&mut self, source_entity: &Entity, timestamp: i64) {
+        let key = resolve_entity_key(&mut self.encounter, source_entity);
         let entity = self
             .encounter
             .entities
-            .entry(source_entity.name.clone())
+            .entry(key)
             .or_insert_with(|| {
                 let mut entity = encounter_entity_from_entity(source_entity);
                 entity.skill_stats = SkillStats {
@@ -1610,6 +1808,7 @@ impl EncounterState {
                 entity
             });
         entity.skill_stats.counters += 1;
+        self.wipe_tracker.on_counter(timestamp);
     }
 
     pub fn on_identity_gain(&mut self, pkt: &PKTIdentityGaugeChangeNotify) {
@@ -1630,11 +1829,7 @@ impl EncounterState {
             }
         }
 
-        if let Some(entity) = self
-            .encounter
-            .entities
-            .get_mut(&self.encounter.local_player)
-        {
+        if let Some(entity) = self.encounter.entity_by_name(&self.encounter.local_player) {
             self.identity_log
                 .entry(entity.name.clone())
                 .or_default()
@@ -1699,12 +1894,10 @@ impl EncounterState {
         if target_entity.entity_type == EntityType::BOSS
             && target_entity.name == self.encounter.current_boss_name
         {
-            self.encounter
-                .entities
-                .entry(target_entity.name.clone())
-                .and_modify(|e| {
-                    e.current_shield = shield;
-                });
+            let key = resolve_entity_key(&mut self.encounter, target_entity);
+            if let Some(e) = self.encounter.entities.get_mut(&key) {
+                e.current_shield = shield;
+            }
         }
     }
 
@@ -1718,16 +1911,18 @@ impl EncounterState {
         if source_entity.entity_type == EntityType::PLAYER
             && target_entity.entity_type == EntityType::PLAYER
         {
+            let target_key = resolve_entity_key(&mut self.encounter, target_entity);
             let mut target_entity_state = self
                 .encounter
                 .entities
-                .entry(target_entity.name.clone())
+                .entry(target_key.clone())
                 .or_insert_with(|| encounter_entity_from_entity(target_entity))
                 .to_owned();
+            let source_key = resolve_entity_key(&mut self.encounter, source_entity);
             let mut source_entity_state = self
                 .encounter
                 .entities
-                .entry(source_entity.name.clone())
+                .entry(source_key.clone())
                 .or_insert_with(|| encounter_entity_from_entity(source_entity))
                 .to_owned();
 
@@ -1769,9 +1964,7 @@ impl EncounterState {
                     .and_modify(|e| *e += shield)
                     .or_insert(shield);
 
-                self.encounter
-                    .entities
-                    .insert(source_entity_state.name.clone(), source_entity_state);
+                self.encounter.insert_entity(source_key, source_entity_state);
             } else {
                 target_entity_state.damage_stats.shields_received += shield;
                 source_entity_state.damage_stats.shields_given += shield;
@@ -1788,12 +1981,8 @@ impl EncounterState {
                     .and_modify(|e| *e += shield)
                     .or_insert(shield);
 
-                self.encounter
-                    .entities
-                    .insert(target_entity_state.name.clone(), target_entity_state);
-                self.encounter
-                    .entities
-                    .insert(source_entity_state.name.clone(), source_entity_state);
+                self.encounter.insert_entity(target_key, target_entity_state);
+                self.encounter.insert_entity(source_key, source_entity_state);
             }
 
             self.encounter.encounter_damage_stats.total_shielding += shield;
@@ -1810,16 +1999,18 @@ impl EncounterState {
         if source_entity.entity_type == EntityType::PLAYER
             && target_entity.entity_type == EntityType::PLAYER
         {
+            let target_key = resolve_entity_key(&mut self.encounter, target_entity);
             let mut target_entity_state = self
                 .encounter
                 .entities
-                .entry(target_entity.name.clone())
+                .entry(target_key.clone())
                 .or_insert_with(|| encounter_entity_from_entity(target_entity))
                 .to_owned();
+            let source_key = resolve_entity_key(&mut self.encounter, source_entity);
             let mut source_entity_state = self
                 .encounter
                 .entities
-                .entry(source_entity.name.clone())
+                .entry(source_key.clone())
                 .or_insert_with(|| encounter_entity_from_entity(source_entity))
                 .to_owned();
 
@@ -1839,9 +2030,7 @@ impl EncounterState {
                     .and_modify(|e| *e += shield_removed)
                     .or_insert(shield_removed);
 
-                self.encounter
-                    .entities
-                    .insert(source_entity_state.name.clone(), source_entity_state);
+                self.encounter.insert_entity(source_key, source_entity_state);
             } else {
                 target_entity_state.damage_stats.damage_absorbed += shield_removed;
                 source_entity_state.damage_stats.damage_absorbed_on_others += shield_removed;
@@ -1858,12 +2047,8 @@ impl EncounterState {
                     .and_modify(|e| *e += shield_removed)
                     .or_insert(shield_removed);
 
-                self.encounter
-                    .entities
-                    .insert(target_entity_state.name.clone(), target_entity_state);
-                self.encounter
-                    .entities
-                    .insert(source_entity_state.name.clone(), source_entity_state);
+                self.encounter.insert_entity(target_key, target_entity_state);
+                self.encounter.insert_entity(source_key, source_entity_state);
             }
 
             self.encounter
@@ -1876,10 +2061,10 @@ impl EncounterState {
         if !manual {
             if self.encounter.fight_start == 0
                 || self.encounter.current_boss_name.is_empty()
-                || !self
+                || self
                     .encounter
-                    .entities
-                    .contains_key(&self.encounter.current_boss_name)
+                    .entity_by_name(&self.encounter.current_boss_name)
+                    .is_none()
                 || !self
                     .encounter
                     .entities
@@ -1891,8 +2076,7 @@ impl EncounterState {
 
             if let Some(current_boss) = self
                 .encounter
-                .entities
-                .get(&self.encounter.current_boss_name)
+                .entity_by_name(&self.encounter.current_boss_name)
             {
                 if current_boss.current_hp == current_boss.max_hp {
                     return;
@@ -1905,13 +2089,13 @@ impl EncounterState {
         }
 
         let mut encounter = self.encounter.clone();
-        let mut path = self
+        let settings_path = self
             .window
             .app_handle()
             .path_resolver()
             .resource_dir()
-            .expect("could not get resource dir");
-        path.push("encounters.db");
+            .expect("could not get resource dir")
+            .join("settings.json");
         let prev_stagger = self.prev_stagger;
 
         let damage_log = self.damage_log.clone();
@@ -1929,6 +2113,7 @@ impl EncounterState {
         let ntp_fight_start = self.ntp_fight_start;
 
         let rdps_valid = self.rdps_valid;
+        let wipe_report = self.wipe_report.clone();
 
         let skill_cast_log = self.skill_tracker.get_cast_log();
 
@@ -1942,7 +2127,13 @@ impl EncounterState {
             raid_clear, self.raid_difficulty, encounter.current_boss_name
         );
 
-        encounter.current_boss_name = update_current_boss_name(&encounter.current_boss_name);
+        if let Some(raid_gate) = self.raid_gate() {
+            encounter.raid = Some(raid_gate.raid.to_string());
+            encounter.gate = Some(raid_gate.gate);
+            if let Some(display_name) = raid_gate.display_name {
+                encounter.current_boss_name = display_name.to_string();
+            }
+        }
 
         let window = self.window.clone();
         task::spawn(async move {
@@ -1968,39 +2159,88 @@ impl EncounterState {
                 None
             };
 
-            let mut conn = Connection::open(path).expect("failed to open database");
-            let tx = conn.transaction().expect("failed to create transaction");
-
-            let encounter_id = insert_data(
-                &tx,
-                encounter,
-                prev_stagger,
-                damage_log,
-                identity_log,
-                cast_log,
-                boss_hp_log,
-                stagger_log,
-                stagger_intervals,
-                raid_clear,
-                party_info,
-                raid_difficulty,
-                region,
-                player_infos,
-                meter_version,
-                ntp_fight_start,
-                rdps_valid,
-                manual,
-                skill_cast_log,
-            );
+            // the write waits on the writer thread, keep that off the async workers
+            let app_handle = window.app_handle();
+            let result = task::spawn_blocking(move || {
+                let database = app_handle.state::<Database>();
+                database.write(move |conn| {
+                    let tx = conn.transaction()?;
+
+                    let saved = insert_data(
+                        &tx,
+                        encounter,
+                        prev_stagger,
+                        damage_log,
+                        identity_log,
+                        cast_log,
+                        boss_hp_log,
+                        stagger_log,
+                        stagger_intervals,
+                        raid_clear,
+                        party_info,
+                        raid_difficulty,
+                        region,
+                        player_infos,
+                        meter_version,
+                        ntp_fight_start,
+                        rdps_valid,
+                        manual,
+                        skill_cast_log,
+                        wipe_report,
+                    );
+                    // dropping the transaction rolls the encounter back
+                    if saved.is_err() {
+                        return Ok(saved);
+                    }
 
-            tx.commit().expect("failed to commit transaction");
-            info!("saved to db");
+                    tx.commit()?;
+                    info!("saved to db");
+
+                    // settings can change while the meter runs, so they're read on every save
+                    let retention = std::fs::read_to_string(&settings_path)
+                        .ok()
+                        .and_then(|contents| serde_json::from_str::<Settings>(&contents).ok())
+                        .map(|settings| settings.retention);
+                    if let Some(retention) = retention {
+                        let now = Utc::now().timestamp_millis();
+                        if let Err(e) = crate::retention::apply_retention(conn, &retention, now) {
+                            warn!("could not apply retention rules: {}", e);
+                        }
+                    }
+
+                    Ok(saved)
+                })
+            })
+            .await;
+            let saved = match result {
+                Ok(saved) => saved.map_err(AppError::from).and_then(|saved| saved),
+                Err(e) => {
+                    error!("save task failed: {}", e);
+                    return;
+                }
+            };
+            let (encounter_id, personal_bests) = match saved {
+                Ok(saved) => saved,
+                Err(e) => {
+                    error!("failed to save encounter: {}", e);
+                    if let Err(e) = window.emit("db-error", &e) {
+                        error!("failed to emit db-error: {}", e);
+                    }
+                    return;
+                }
+            };
 
             if raid_clear {
                 window
                     .emit("clear-encounter", encounter_id)
                     .expect("failed to emit clear-encounter");
             }
+
+            if !personal_bests.is_empty() {
+                window
+                    .emit("personal-best", personal_bests)
+                    .expect("failed to emit personal-best");
+            }
         });
     }
 }
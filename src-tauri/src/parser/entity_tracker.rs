@@ -1,11 +1,12 @@
 use crate::parser::id_tracker::IdTracker;
 use crate::parser::models::EntityType::*;
-use crate::parser::models::{EncounterEntity, EntityType, Esther, LocalInfo, LocalPlayer, PassiveOption, ESTHER_DATA, NPC_DATA, SKILL_DATA};
+use crate::parser::models::{Encounter, EntityType, Esther, LocalInfo, LocalPlayer, PassiveOption, ESTHER_DATA, NPC_DATA, SKILL_DATA};
 use crate::parser::party_tracker::PartyTracker;
 use crate::parser::status_tracker::{
     build_status_effect, StatusEffectDetails, StatusEffectTargetType, StatusEffectType,
     StatusTracker,
 };
+use crate::parser::utils::find_encounter_entity;
 
 use chrono::{DateTime, Utc};
 use hashbrown::HashMap;
@@ -277,13 +278,13 @@ impl EntityTracker {
     pub fn party_status_effect_add(
         &mut self,
         pkt: PKTPartyStatusEffectAddNotify,
-        entities: &HashMap<String, EncounterEntity>,
+        encounter: &Encounter,
     ) -> Vec<StatusEffectDetails> {
         let timestamp = Utc::now();
         let mut shields: Vec<StatusEffectDetails> = Vec::new();
         for sed in pkt.status_effect_datas {
             let entity = self.get_source_entity(sed.source_id);
-            let encounter_entity = entities.get(&entity.name);
+            let encounter_entity = find_encounter_entity(encounter, &entity);
             // println!("entity: {:?}", entity);
             let status_effect = build_status_effect(
                 sed,
@@ -489,11 +490,11 @@ impl EntityTracker {
         sed: &StatusEffectData,
         target_id: u64,
         timestamp: DateTime<Utc>,
-        entities: Option<&HashMap<String, EncounterEntity>>,
+        encounter: Option<&Encounter>,
     ) -> StatusEffectDetails {
         let source_entity = self.get_source_entity(sed.source_id);
         let source_encounter_entity =
-            entities.and_then(|entities| entities.get(&source_entity.name));
+            encounter.and_then(|encounter| find_encounter_entity(encounter, &source_entity));
         let status_effect = build_status_effect(
             sed.clone(),
             target_id,
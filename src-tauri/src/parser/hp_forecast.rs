@@ -0,0 +1,206 @@
+use crate::parser::models::*;
+
+// only the most recent stretch of active seconds is used for the rate
+const WINDOW_S: i32 = 60;
+// a longer gap between samples means the boss was untargetable or out of reach
+const MAX_GAP_S: i32 = 3;
+// seconds of active damage before the estimate is fully trusted
+const FULL_CONFIDENCE_S: f64 = 30.0;
+
+pub fn forecast_boss_hp(
+    boss: &EncounterEntity,
+    log: &[BossHpLog],
+    elapsed: i64,
+    enrage: Option<u32>,
+) -> Option<BossHpForecast> {
+    let last = log.last()?;
+
+    // hp going back up is a bar refill or a new phase, older samples no longer apply
+    let start = log
+        .windows(2)
+        .rposition(|pair| pair[1].hp > pair[0].hp)
+        .map_or(0, |i| i + 1);
+    let samples = &log[start..];
+
+    // walk backwards collecting per second rates, skipping flat stretches where the
+    // boss was invulnerable or the hp bar was gated
+    let mut rates: Vec<f64> = Vec::new();
+    let mut active_s = 0;
+    let mut damage = 0;
+    for pair in samples.windows(2).rev() {
+        let dt = pair[1].time - pair[0].time;
+        let drop = pair[0].hp - pair[1].hp;
+        if dt <= 0 || dt > MAX_GAP_S || drop <= 0 {
+            continue;
+        }
+        active_s += dt;
+        damage += drop;
+        rates.push(drop as f64 / dt as f64);
+        if active_s >= WINDOW_S {
+            break;
+        }
+    }
+
+    let dps = if active_s > 0 {
+        damage as f64 / active_s as f64
+    } else {
+        0.0
+    };
+
+    let time_to_kill = if dps > 0.0 {
+        Some((last.hp as f64 / dps).ceil() as i64)
+    } else {
+        None
+    };
+
+    let enrage_time = enrage.map(|seconds| seconds as i64);
+    let required_dps = enrage_time.and_then(|enrage_time| {
+        let remaining = enrage_time - elapsed;
+        if remaining > 0 {
+            Some(last.hp as f64 / remaining as f64)
+        } else {
+            None
+        }
+    });
+
+    Some(BossHpForecast {
+        boss_name: boss.name.clone(),
+        current_hp: last.hp,
+        max_hp: boss.max_hp,
+        dps,
+        elapsed,
+        time_to_kill,
+        predicted_kill_time: time_to_kill.map(|ttk| elapsed + ttk),
+        enrage_time,
+        required_dps,
+        confidence: confidence(&rates, active_s),
+    })
+}
+
+// scales with how much active time was sampled and drops as the per second rate gets noisier
+fn confidence(rates: &[f64], active_s: i32) -> f64 {
+    if rates.len() < 2 {
+        return 0.0;
+    }
+
+    let mean = rates.iter().sum::<f64>() / rates.len() as f64;
+    if mean <= 0.0 {
+        return 0.0;
+    }
+    let variance = rates.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / rates.len() as f64;
+    let coefficient_of_variation = variance.sqrt() / mean;
+
+    let coverage = (active_s as f64 / FULL_CONFIDENCE_S).min(1.0);
+    coverage / (1.0 + coefficient_of_variation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boss(max_hp: i64) -> EncounterEntity {
+        EncounterEntity {
+            name: "Boss".to_string(),
+            max_hp,
+            ..Default::default()
+        }
+    }
+
+    fn hp_log(samples: &[(i32, i64)]) -> Vec<BossHpLog> {
+        samples
+            .iter()
+            .map(|&(time, hp)| BossHpLog::new(time, hp, hp as f32 / 1000.0))
+            .collect()
+    }
+
+    #[test]
+    fn steady_damage_forecasts_kill_and_required_dps() {
+        let log = hp_log(&[(0, 1000), (1, 900), (2, 800), (3, 700)]);
+        let forecast = forecast_boss_hp(&boss(1000), &log, 3, Some(10)).unwrap();
+
+        assert_eq!(forecast.dps, 100.0);
+        assert_eq!(forecast.time_to_kill, Some(7));
+        assert_eq!(forecast.predicted_kill_time, Some(10));
+        assert_eq!(forecast.enrage_time, Some(10));
+        assert_eq!(forecast.required_dps, Some(100.0));
+    }
+
+    #[test]
+    fn zero_elapsed_spreads_hp_over_the_whole_enrage() {
+        let log = hp_log(&[(0, 1000)]);
+        let forecast = forecast_boss_hp(&boss(1000), &log, 0, Some(100)).unwrap();
+
+        assert_eq!(forecast.dps, 0.0);
+        assert_eq!(forecast.time_to_kill, None);
+        assert_eq!(forecast.required_dps, Some(10.0));
+        assert_eq!(forecast.confidence, 0.0);
+    }
+
+    #[test]
+    fn zero_dps_has_no_kill_time() {
+        // invulnerable the whole time
+        let log = hp_log(&[(0, 1000), (1, 1000), (2, 1000), (3, 1000)]);
+        let forecast = forecast_boss_hp(&boss(1000), &log, 3, None).unwrap();
+
+        assert_eq!(forecast.dps, 0.0);
+        assert_eq!(forecast.time_to_kill, None);
+        assert_eq!(forecast.predicted_kill_time, None);
+        assert_eq!(forecast.required_dps, None);
+        assert_eq!(forecast.confidence, 0.0);
+    }
+
+    #[test]
+    fn boss_already_at_zero_needs_nothing() {
+        let log = hp_log(&[(0, 200), (1, 100), (2, 0)]);
+        let forecast = forecast_boss_hp(&boss(1000), &log, 2, Some(10)).unwrap();
+
+        assert_eq!(forecast.time_to_kill, Some(0));
+        assert_eq!(forecast.predicted_kill_time, Some(2));
+        assert_eq!(forecast.required_dps, Some(0.0));
+    }
+
+    #[test]
+    fn past_enrage_has_no_required_dps() {
+        let log = hp_log(&[(0, 1000), (1, 900), (2, 800)]);
+        let forecast = forecast_boss_hp(&boss(1000), &log, 12, Some(10)).unwrap();
+
+        assert_eq!(forecast.enrage_time, Some(10));
+        assert_eq!(forecast.required_dps, None);
+    }
+
+    #[test]
+    fn refill_restarts_the_sample() {
+        let log = hp_log(&[(0, 1000), (1, 500), (2, 1000), (3, 990), (4, 980)]);
+        let forecast = forecast_boss_hp(&boss(1000), &log, 4, None).unwrap();
+
+        assert_eq!(forecast.dps, 10.0);
+        assert_eq!(forecast.time_to_kill, Some(98));
+    }
+
+    #[test]
+    fn empty_log_has_no_forecast() {
+        assert!(forecast_boss_hp(&boss(1000), &[], 0, None).is_none());
+    }
+
+    #[test]
+    fn confidence_needs_two_rates() {
+        assert_eq!(confidence(&[], 0), 0.0);
+        assert_eq!(confidence(&[100.0], 1), 0.0);
+    }
+
+    #[test]
+    fn confidence_is_zero_without_damage() {
+        assert_eq!(confidence(&[0.0, 0.0], 2), 0.0);
+    }
+
+    #[test]
+    fn confidence_grows_with_coverage_and_drops_with_noise() {
+        let steady = confidence(&[100.0; 30], 30);
+        assert_eq!(steady, 1.0);
+        assert_eq!(confidence(&[100.0; 15], 15), 0.5);
+
+        let noisy = confidence(&[50.0, 150.0].repeat(15), 30);
+        assert!(noisy < steady);
+        assert!(noisy > 0.0);
+    }
+}
@@ -1,5 +1,6 @@
 pub mod encounter_state;
 mod entity_tracker;
+mod hp_forecast;
 mod id_tracker;
 pub mod models;
 mod party_tracker;
@@ -8,14 +9,15 @@ mod skill_tracker;
 mod stats_api;
 mod status_tracker;
 mod utils;
+mod wipe_report;
+
+pub use utils::{export_encounter_data, import_encounter_data};
 
 use self::models::{Settings, TripodIndex, TripodLevel};
 use crate::parser::encounter_state::EncounterState;
 use crate::parser::entity_tracker::{get_current_and_max_hp, EntityTracker};
 use crate::parser::id_tracker::IdTracker;
-use crate::parser::models::{
-    DamageData, EntityType, Identity, LocalInfo, LocalPlayer, Stagger, VALID_ZONES,
-};
+use crate::parser::models::{DamageData, EntityType, Identity, LocalInfo, Stagger, VALID_ZONES};
 use crate::parser::party_tracker::PartyTracker;
 use crate::parser::stats_api::{StatsApi, API_URL};
 use crate::parser::status_tracker::{
@@ -211,7 +213,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                     parse_pkt(&data, PKTCounterAttackNotify::new, "PKTCounterAttackNotify")
                 {
                     if let Some(entity) = entity_tracker.entities.get(&pkt.source_id) {
-                        state.on_counterattack(entity);
+                        state.on_counterattack(entity, Utc::now().timestamp_millis());
                     }
                 }
             }
@@ -299,17 +301,18 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                         entity.character_id
                     );
 
-                    local_info
+                    let local_player = local_info
                         .local_players
                         .entry(entity.character_id)
-                        .and_modify(|e| {
-                            e.name = entity.name.clone();
-                            e.count += 1;
-                        })
-                        .or_insert(LocalPlayer {
-                            name: entity.name.clone(),
-                            count: 1,
-                        });
+                        .or_default();
+                    local_player.name = entity.name.clone();
+                    local_player.count += 1;
+                    local_player.class_id = entity.class_id;
+                    local_player.class = get_class_from_id(&entity.class_id);
+                    if entity.gear_level > 0.0 {
+                        local_player.gear_score = entity.gear_level;
+                    }
+                    local_player.last_seen = Utc::now().timestamp_millis();
 
                     write_local_players(&local_info, &local_player_path)?;
 
@@ -658,7 +661,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                 ) {
                     // info!("{:?}", pkt);
                     let shields =
-                        entity_tracker.party_status_effect_add(pkt, &state.encounter.entities);
+                        entity_tracker.party_status_effect_add(pkt, &state.encounter);
                     for status_effect in shields {
                         let source = entity_tracker.get_source_entity(status_effect.source_id);
                         let target_id =
@@ -731,7 +734,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                         &pkt.status_effect_data,
                         pkt.object_id,
                         Utc::now(),
-                        Some(&state.encounter.entities),
+                        Some(&state.encounter),
                     );
                     if status_effect.status_effect_type == StatusEffectType::Shield {
                         let source = entity_tracker.get_source_entity(status_effect.source_id);
@@ -942,14 +945,10 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                     // info!("{:?}", pkt);
                     if let Some(object_id) = id_tracker.borrow().get_entity_id(pkt.character_id) {
                         if let Some(entity) = entity_tracker.get_entity_ref(object_id) {
-                            state
-                                .encounter
-                                .entities
-                                .entry(entity.name.clone())
-                                .and_modify(|e| {
-                                    e.current_hp = pkt.cur_hp;
-                                    e.max_hp = pkt.max_hp;
-                                });
+                            if let Some(e) = state.encounter.entity_by_name_mut(&entity.name) {
+                                e.current_hp = pkt.cur_hp;
+                                e.max_hp = pkt.max_hp;
+                            }
                         }
                         for se in pkt.status_effect_datas.iter() {
                             let val = get_status_effect_value(&se.value);
@@ -994,6 +993,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
             }
             let mut clone = state.encounter.clone();
             let damage_valid = state.damage_is_valid;
+            let boss_forecast = state.boss_hp_forecast();
             let window = window.clone();
 
             let party_info: Option<HashMap<i32, Vec<String>>> =
@@ -1027,7 +1027,7 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
 
             tokio::task::spawn(async move {
                 if !clone.current_boss_name.is_empty() {
-                    let current_boss = clone.entities.get(&clone.current_boss_name).cloned();
+                    let current_boss = clone.entity_by_name(&clone.current_boss_name).cloned();
                     if let Some(mut current_boss) = current_boss {
                         if boss_dead {
                             current_boss.is_dead = true;
@@ -1050,6 +1050,12 @@ pub fn start(window: Window<Wry>, port: u16, settings: Option<Settings>) -> Resu
                         .emit("encounter-update", Some(clone))
                         .expect("failed to emit encounter-update");
 
+                    if boss_forecast.is_some() {
+                        window
+                            .emit("boss-forecast", boss_forecast)
+                            .expect("failed to emit boss-forecast");
+                    }
+
                     if !damage_valid {
                         window
                             .emit("invalid-damage", "")
@@ -7,8 +7,9 @@ use hashbrown::{HashMap, HashSet};
 use lazy_static::lazy_static;
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
+use serde_with::base64::Base64;
 use serde_with::serde_as;
-use serde_with::DefaultOnError;
+use serde_with::{DefaultOnError, PickFirst, Same};
 
 pub const DB_VERSION: i32 = 5;
 
@@ -67,7 +68,11 @@ pub struct Encounter {
     pub last_combat_packet: i64,
     pub fight_start: i64,
     pub local_player: String,
+    // keyed by entity key, see utils::entity_key
     pub entities: HashMap<String, EncounterEntity>,
+    // display name -> entity key
+    #[serde(skip)]
+    pub entity_names: HashMap<String, String>,
     pub current_boss_name: String,
     pub current_boss: Option<EncounterEntity>,
     pub encounter_damage_stats: EncounterDamageStats,
@@ -77,6 +82,52 @@ pub struct Encounter {
     pub cleared: bool,
     pub boss_only_damage: bool,
     pub sync: Option<String>,
+    pub raid: Option<String>,
+    pub gate: Option<u32>,
+}
+
+impl Encounter {
+    pub fn entity_key(&self, name: &str) -> Option<String> {
+        if let Some(key) = self.entity_names.get(name) {
+            if self.entities.get(key).is_some_and(|e| e.name == name) {
+                return Some(key.clone());
+            }
+        }
+
+        // index can lag behind renames, fall back to a scan
+        self.entities
+            .iter()
+            .find(|(_, e)| e.name == name)
+            .map(|(key, _)| key.clone())
+    }
+
+    pub fn entity_by_name(&self, name: &str) -> Option<&EncounterEntity> {
+        self.entity_key(name).and_then(|key| self.entities.get(&key))
+    }
+
+    pub fn entity_by_name_mut(&mut self, name: &str) -> Option<&mut EncounterEntity> {
+        let key = self.entity_key(name)?;
+        self.entities.get_mut(&key)
+    }
+
+    pub fn insert_entity(&mut self, key: String, entity: EncounterEntity) {
+        self.entity_names.insert(entity.name.clone(), key.clone());
+        self.entities.insert(key, entity);
+    }
+
+    pub fn remove_entity_by_name(&mut self, name: &str) -> Option<EncounterEntity> {
+        let key = self.entity_key(name)?;
+        self.entity_names.remove(name);
+        self.entities.remove(&key)
+    }
+
+    pub fn reindex_entities(&mut self) {
+        self.entity_names = self
+            .entities
+            .iter()
+            .map(|(key, e)| (e.name.clone(), key.clone()))
+            .collect();
+    }
 }
 
 #[derive(Debug, Serialize, Clone, Default)]
@@ -162,6 +213,14 @@ pub struct Skill {
     pub front_attacks: i64,
     pub back_attack_damage: i64,
     pub front_attack_damage: i64,
+    pub flank_attacks: i64,
+    pub flank_attack_damage: i64,
+    pub overkill_damage: i64,
+    pub misses: i64,
+    pub dodges: i64,
+    pub immunes: i64,
+    pub reflects: i64,
+    pub damage_shares: i64,
     pub dps: i64,
     pub cast_log: Vec<i32>,
     pub tripod_index: Option<TripodIndex>,
@@ -178,10 +237,21 @@ pub struct Skill {
     pub rdps_damage_received_support: i64,
     pub rdps_damage_given: i64,
     pub skill_cast_log: Vec<SkillCast>,
+    pub damage_by_target: HashMap<u32, TargetDamage>,
+    pub damage_by_attribute: HashMap<u8, i64>,
+    pub damage_by_type: HashMap<u8, i64>,
     #[serde(skip)]
     pub last_timestamp: i64,
 }
 
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TargetDamage {
+    pub damage: i64,
+    pub hits: i64,
+    pub crits: i64,
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase", default)]
 pub struct TripodData {
@@ -252,6 +322,10 @@ pub struct DamageStats {
     pub crit_damage: i64,
     pub back_attack_damage: i64,
     pub front_attack_damage: i64,
+    pub flank_attack_damage: i64,
+    pub overkill_damage: i64,
+    // shared hits without a source skill, not included in damage_dealt
+    pub damage_share_damage: i64,
     pub shields_given: u64,
     pub shields_received: u64,
     pub damage_absorbed: u64,
@@ -270,6 +344,11 @@ pub struct DamageStats {
     pub rdps_damage_received: i64,
     pub rdps_damage_received_support: i64,
     pub rdps_damage_given: i64,
+    pub damage_by_target: HashMap<u32, TargetDamage>,
+    // keyed by damageattr (none, fire, ice, electricity, wind, earth, dark, holy)
+    pub damage_by_attribute: HashMap<u8, i64>,
+    // keyed by damagetype (physics, magic)
+    pub damage_by_type: HashMap<u8, i64>,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -283,6 +362,18 @@ pub struct SkillStats {
     pub counters: i64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub identity_stats: Option<String>,
+    #[serde(default)]
+    pub flank_attacks: i64,
+    #[serde(default)]
+    pub misses: i64,
+    #[serde(default)]
+    pub dodges: i64,
+    #[serde(default)]
+    pub immunes: i64,
+    #[serde(default)]
+    pub reflects: i64,
+    #[serde(default)]
+    pub damage_shares: i64,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -301,6 +392,8 @@ pub struct SkillHit {
     pub crit: bool,
     pub back_attack: bool,
     pub front_attack: bool,
+    #[serde(default)]
+    pub flank_attack: bool,
     pub buffed_by: Vec<u32>,
     pub debuffed_by: Vec<u32>,
     pub rdps_damage_received: i64,
@@ -394,6 +487,11 @@ pub struct EncounterMisc {
     pub ntp_fight_start: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub manual_save: Option<bool>,
+    // npc_id -> name for targets in damage_by_target
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_names: Option<HashMap<u32, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wipe_report: Option<WipeReport>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -405,6 +503,89 @@ pub struct BossHpLog {
     pub p: f32,
 }
 
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BossHpForecast {
+    pub boss_name: String,
+    pub current_hp: i64,
+    pub max_hp: i64,
+    // damage per second against the bar, only counting seconds where hp went down
+    pub dps: f64,
+    // seconds, all relative to fight start
+    pub elapsed: i64,
+    pub time_to_kill: Option<i64>,
+    pub predicted_kill_time: Option<i64>,
+    pub enrage_time: Option<i64>,
+    // damage per second needed to finish the bar before enrage
+    pub required_dps: Option<f64>,
+    // 0 to 1
+    pub confidence: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WipeReport {
+    pub boss_name: String,
+    pub boss_hp: i64,
+    pub boss_max_hp: i64,
+    pub hp_percent: f64,
+    // bosses of multi boss gates count as phases, starting at 1
+    pub phase: u32,
+    // ms relative to fight start
+    pub wipe_time: i64,
+    pub deaths: Vec<WipeDeath>,
+    pub killing_skills: Vec<WipeKillingSkill>,
+    // uptimes over the last 30 seconds before the wipe
+    pub buff_uptime: Vec<WipeBuffUptime>,
+    // none when the stagger bar wasn't tracked
+    pub stagger_check_missed: Option<bool>,
+    pub counters: i64,
+    pub recent_counters: i64,
+    // casts of skills that were countered earlier in the pull but not this time
+    pub missed_counters: Vec<WipeMissedCounter>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WipeDeath {
+    pub name: String,
+    pub class_id: u32,
+    // ms relative to fight start
+    pub time: i64,
+    pub skill_id: Option<u32>,
+    pub skill_name: Option<String>,
+    pub source: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WipeKillingSkill {
+    pub source: String,
+    pub skill_id: u32,
+    pub skill_name: String,
+    pub deaths: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WipeMissedCounter {
+    pub skill_id: u32,
+    pub skill_name: String,
+    // ms relative to fight start
+    pub time: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WipeBuffUptime {
+    pub name: String,
+    pub class_id: u32,
+    pub damage: i64,
+    pub buffed_by_support: f64,
+    pub buffed_by_identity: f64,
+    pub debuffed_by_support: f64,
+}
+
 impl BossHpLog {
     pub fn new(time: i32, hp: i64, p: f32) -> Self {
         Self { time, hp, p }
@@ -637,6 +818,8 @@ pub struct EncounterPreview {
     pub my_dps: i64,
     pub favorite: bool,
     pub cleared: bool,
+    pub raid: Option<String>,
+    pub gate: Option<u32>,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -650,6 +833,7 @@ pub struct EncountersOverview {
 #[serde(rename_all = "camelCase", default)]
 pub struct SearchFilter {
     pub bosses: Vec<String>,
+    pub raids: Vec<String>,
     pub min_duration: i32,
     pub max_duration: i32,
     pub cleared: bool,
@@ -658,6 +842,7 @@ pub struct SearchFilter {
     pub boss_only_damage: bool,
     pub sort: String,
     pub order: u8,
+    pub trashed: bool,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -669,6 +854,8 @@ pub struct Settings {
     pub logs: LogTabs,
     pub buffs: BuffSettings,
     pub sync: SyncSettings,
+    pub backup: BackupSettings,
+    pub retention: RetentionSettings,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -722,6 +909,7 @@ pub struct GeneralSettings {
     pub boss_only_damage_default_on: bool,
     pub start_on_boot: bool,
     pub logs_per_page: i32,
+    pub weekly_tray_summary: bool,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -766,6 +954,11 @@ pub struct LogTabs {
     pub percent_brand: bool,
     pub counters: bool,
     pub min_encounter_duration: i32,
+    // trashed logs older than this are deleted for good, 0 keeps them until emptied
+    #[serde(default = "default_trash_retention_days")]
+    pub trash_retention_days: u32,
+    // entities of logs older than this move to the archive db, 0 never archives
+    pub archive_after_days: u32,
     #[serde(default = "default_true")]
     pub rdps_split_party: bool,
     #[serde(default = "default_true")]
@@ -873,6 +1066,79 @@ pub struct SyncSettings {
     pub visibility: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BackupSettings {
+    pub enabled: bool,
+    pub interval_hours: u32,
+    // copies kept of each kind of backup
+    pub keep: u32,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_hours: 24,
+            keep: 5,
+        }
+    }
+}
+
+// off by default since it deletes logs, matching logs go to the trash
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetentionSettings {
+    pub enabled: bool,
+    pub keep_favorites: bool,
+    pub keep_personal_bests: bool,
+    pub rules: Vec<RetentionRule>,
+}
+
+impl Default for RetentionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            keep_favorites: true,
+            keep_personal_bests: true,
+            rules: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum RetentionRule {
+    // pulls older than the given number of days
+    #[serde(rename_all = "camelCase")]
+    OlderThan {
+        days: u32,
+        #[serde(default)]
+        uncleared_only: bool,
+        #[serde(default)]
+        boss: Option<String>,
+    },
+    // only the best pulls by local player dps for each boss and difficulty
+    #[serde(rename_all = "camelCase")]
+    KeepBest {
+        count: u32,
+        #[serde(default)]
+        boss: Option<String>,
+    },
+    // pulls shorter than the given number of seconds
+    #[serde(rename_all = "camelCase")]
+    ShorterThan { seconds: u32 },
+}
+
+#[derive(Default, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DbBackup {
+    pub name: String,
+    pub kind: String,
+    pub created: i64,
+    pub size: u64,
+}
+
 #[derive(Default, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EncounterDbInfo {
@@ -881,6 +1147,414 @@ pub struct EncounterDbInfo {
     pub total_encounters_filtered: i32,
 }
 
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonalBest {
+    pub name: String,
+    pub class_id: u32,
+    pub class: String,
+    pub boss: String,
+    pub difficulty: String,
+    // "dps", "rdps", "clear_time" (ms, lower is better) or "max_hit"
+    pub kind: String,
+    pub value: i64,
+    // the record this one replaced, only set on freshly saved records
+    pub previous: Option<i64>,
+    // skill behind the biggest hit
+    pub skill_id: Option<u32>,
+    pub encounter_id: i64,
+    pub fight_start: i64,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerRanking {
+    pub name: String,
+    pub class_id: u32,
+    pub class: String,
+    pub spec: Option<String>,
+    pub gear_score: f32,
+    pub dps: i64,
+    // 1 is the best local log
+    pub rank: i64,
+    // number of local logs compared against, including this one
+    pub total: i64,
+    // 100 is the best local log, 0 the worst
+    pub percentile: f64,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CharacterTrends {
+    pub name: String,
+    pub class_id: u32,
+    pub class: String,
+    pub bosses: Vec<BossTrend>,
+    pub spec_changes: Vec<SpecChange>,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BossTrend {
+    pub boss: String,
+    pub difficulty: Option<String>,
+    pub weeks: Vec<WeeklyTrend>,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WeeklyTrend {
+    // monday 00:00 utc of the calendar week, in ms
+    pub week_start: i64,
+    pub encounters: i64,
+    pub average_dps: i64,
+    pub best_dps: i64,
+    pub gear_score: f32,
+    pub crit_rate: f64,
+    pub buffed_by_support_percent: f64,
+    pub deaths: i64,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SpecChange {
+    pub encounter_id: i64,
+    pub fight_start: i64,
+    pub previous_spec: Option<String>,
+    pub spec: Option<String>,
+    pub ark_passive_changed: bool,
+    pub ark_passive_data: Option<ArkPassiveData>,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RosterCharacter {
+    pub character_id: u64,
+    pub name: String,
+    pub class_id: u32,
+    pub class: String,
+    pub gear_score: f32,
+    pub last_seen: i64,
+    // times this character was seen as the local player
+    pub count: i32,
+    // gates cleared since the last weekly reset
+    pub clears: Vec<RosterClear>,
+    // gates cleared in an earlier week that are still open this week
+    pub pending: Vec<RosterClear>,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RosterClear {
+    pub raid: String,
+    pub gate: u32,
+    pub difficulty: String,
+    // most recent clear, only set for clears this week
+    pub encounter_id: Option<i64>,
+    pub fight_start: Option<i64>,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WeeklyClears {
+    pub region: Option<String>,
+    // ms timestamps of this week's reset and the next one
+    pub reset_start: i64,
+    pub next_reset: i64,
+    pub characters: Vec<CharacterClears>,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CharacterClears {
+    pub name: String,
+    pub class_id: u32,
+    pub clears: Vec<WeeklyClear>,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WeeklyClear {
+    pub boss: String,
+    pub raid: Option<String>,
+    pub gate: Option<u32>,
+    pub difficulty: String,
+    // first clear of the week
+    pub encounter_id: i64,
+    pub fight_start: i64,
+    // times cleared this week, more than 1 means it was repeated
+    pub clears: i64,
+    // never cleared in an earlier week
+    pub first_clear: bool,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BossProgression {
+    pub boss: String,
+    pub difficulty: Option<String>,
+    pub raid: Option<String>,
+    pub gate: Option<u32>,
+    pub pulls: i64,
+    // lowest hp reached on the furthest phase, 0 to 100
+    pub best_hp_percent: f64,
+    pub best_phase: u32,
+    pub best_encounter_id: Option<i64>,
+    pub total_deaths: i64,
+    pub average_deaths: f64,
+    // ms spent in combat over all pulls
+    pub total_time: i64,
+    pub nights: Vec<ProgNight>,
+    pub curve: Vec<ProgPull>,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgNight {
+    // local midnight of the night, pulls until 6am count towards the previous night
+    pub night: i64,
+    pub pulls: i64,
+    pub combat_time: i64,
+    pub first_pull_start: i64,
+    pub last_pull_end: i64,
+    pub best_hp_percent: f64,
+    pub deaths: i64,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgPull {
+    pub pull: i64,
+    pub encounter_id: i64,
+    pub fight_start: i64,
+    pub duration: i64,
+    pub hp_percent: f64,
+    // 1 based index of the boss reached in multi boss gates
+    pub phase: u32,
+    pub phase_boss: String,
+    pub deaths: i64,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RaidSession {
+    // id of the first encounter of the session
+    pub id: i64,
+    pub start: i64,
+    pub end: i64,
+    pub encounters: i64,
+    // ms, end - start
+    pub total_time: i64,
+    pub combat_time: i64,
+    // time spent between pulls
+    pub downtime: i64,
+    pub gates_cleared: i64,
+    pub wipes: i64,
+    pub players: Vec<SessionPlayer>,
+    pub pulls: Vec<SessionPull>,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionPlayer {
+    pub name: String,
+    pub class_id: u32,
+    pub class: String,
+    pub encounters: i64,
+    pub clears: i64,
+    pub combat_time: i64,
+    pub damage: i64,
+    pub rdps_damage: i64,
+    pub dps: i64,
+    pub rdps: i64,
+    pub deaths: i64,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionPull {
+    pub encounter_id: i64,
+    pub boss: String,
+    pub difficulty: Option<String>,
+    pub fight_start: i64,
+    pub duration: i64,
+    pub cleared: bool,
+}
+
+// 2: gzipped columns are base64 strings instead of byte arrays
+pub const ENCOUNTER_EXPORT_VERSION: u32 = 2;
+
+// portable copy of one encounter's rows
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EncounterExport {
+    pub version: u32,
+    pub meter_version: String,
+    // json columns are gzipped blobs, plain json text for logs from before 1.13.5
+    // both are written as base64, version 1 files used byte arrays and still import
+    pub compressed: bool,
+    pub encounter: ExportedEncounter,
+    pub entities: Vec<ExportedEntity>,
+}
+
+#[serde_as]
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedEncounter {
+    pub last_combat_packet: i64,
+    pub total_damage_dealt: i64,
+    pub top_damage_dealt: i64,
+    pub total_damage_taken: i64,
+    pub top_damage_taken: i64,
+    pub dps: i64,
+    #[serde_as(as = "Option<PickFirst<(Base64, Same)>>")]
+    pub buffs: Option<Vec<u8>>,
+    #[serde_as(as = "Option<PickFirst<(Base64, Same)>>")]
+    pub debuffs: Option<Vec<u8>>,
+    pub total_shielding: i64,
+    pub total_effective_shielding: i64,
+    #[serde_as(as = "Option<PickFirst<(Base64, Same)>>")]
+    pub applied_shield_buffs: Option<Vec<u8>>,
+    pub misc: Option<String>,
+    // DB_VERSION the rows were written with
+    pub version: i32,
+    #[serde_as(as = "Option<PickFirst<(Base64, Same)>>")]
+    pub boss_hp_log: Option<Vec<u8>>,
+    pub stagger_log: Option<String>,
+    pub fight_start: i64,
+    pub current_boss: String,
+    pub duration: i64,
+    pub players: String,
+    pub difficulty: Option<String>,
+    pub local_player: String,
+    pub my_dps: i64,
+    pub cleared: bool,
+    pub boss_only_damage: bool,
+    pub raid: Option<String>,
+    pub gate: Option<u32>,
+}
+
+#[serde_as]
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedEntity {
+    pub name: String,
+    pub npc_id: u32,
+    pub entity_type: String,
+    pub class_id: u32,
+    pub class: String,
+    pub gear_score: f32,
+    pub current_hp: i64,
+    pub max_hp: i64,
+    pub is_dead: bool,
+    #[serde_as(as = "Option<PickFirst<(Base64, Same)>>")]
+    pub skills: Option<Vec<u8>>,
+    #[serde_as(as = "Option<PickFirst<(Base64, Same)>>")]
+    pub damage_stats: Option<Vec<u8>>,
+    pub skill_stats: Option<String>,
+    pub dps: i64,
+    pub character_id: u64,
+    pub engravings: Option<String>,
+    pub gear_hash: Option<String>,
+    pub ark_passive_active: Option<bool>,
+    pub spec: Option<String>,
+    pub ark_passive_data: Option<String>,
+    pub entity_key: String,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EncounterComparison {
+    pub left: ComparedEncounter,
+    pub right: ComparedEncounter,
+    pub players: Vec<PlayerComparison>,
+    // players with no counterpart in the other encounter
+    pub unmatched_left: Vec<String>,
+    pub unmatched_right: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ComparedEncounter {
+    pub id: String,
+    pub boss_name: String,
+    pub difficulty: Option<String>,
+    pub raid: Option<String>,
+    pub gate: Option<u32>,
+    pub fight_start: i64,
+    pub duration: i64,
+    pub cleared: bool,
+    pub dps: i64,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerComparison {
+    // "name" when the same character is in both, "class" when paired by class instead
+    pub matched_by: String,
+    pub left_name: String,
+    pub right_name: String,
+    pub class: String,
+    pub left: PlayerSnapshot,
+    pub right: PlayerSnapshot,
+    // right minus left
+    pub diff: PlayerSnapshot,
+    pub skills: Vec<SkillComparison>,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerSnapshot {
+    pub damage: i64,
+    pub dps: i64,
+    pub damage_percent: f64,
+    pub crit_rate: f64,
+    pub back_attack_rate: f64,
+    pub front_attack_rate: f64,
+    pub buffed_by_support_percent: f64,
+    pub buffed_by_identity_percent: f64,
+    pub buffed_by_hat_percent: f64,
+    pub debuffed_by_support_percent: f64,
+    pub rdps_damage_received: i64,
+    pub rdps_damage_received_support: i64,
+    pub rdps_damage_given: i64,
+    pub casts: i64,
+    pub hits: i64,
+    pub deaths: i64,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillComparison {
+    pub id: u32,
+    pub name: String,
+    pub icon: String,
+    pub left: Option<SkillSnapshot>,
+    pub right: Option<SkillSnapshot>,
+    // right minus left, only when both used the skill
+    pub diff: Option<SkillSnapshot>,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillSnapshot {
+    pub damage: i64,
+    pub dps: i64,
+    pub damage_percent: f64,
+    pub max_damage: i64,
+    pub casts: i64,
+    pub hits: i64,
+    pub crit_rate: f64,
+    pub buffed_by_support_percent: f64,
+    pub buffed_by_identity_percent: f64,
+    pub debuffed_by_support_percent: f64,
+    pub rdps_damage_received: i64,
+    pub rdps_damage_given: i64,
+    pub casts_per_minute: f64,
+    // seconds between casts, how close to cooldown the skill was used
+    pub average_cast_interval: f64,
+}
+
 #[derive(Debug, PartialEq)]
 #[allow(non_camel_case_types)]
 #[repr(i32)]
@@ -976,6 +1650,99 @@ pub struct RdpsBuffData {
     pub rate: f64,
 }
 
+pub struct RaidGate {
+    pub raid: &'static str,
+    pub gate: u32,
+    // name the encounter is saved under, for gates made of several bosses
+    pub display_name: Option<&'static str>,
+    pub bosses: &'static [&'static str],
+}
+
+impl RaidGate {
+    // npc data has no raid info, so a boss name listed under more than one gate (e.g.
+    // Phantom Legion Commander Brelshaza in Brelshaza G4 and Act 2 G2) is settled by the
+    // other bosses seen in the encounter, and by the oldest gate when none of them tell
+    pub fn for_boss(npc_id: u32, boss_names: &[&str]) -> Option<&'static RaidGate> {
+        let gates = RAID_BOSS_DATA.get(&npc_id)?;
+        gates
+            .iter()
+            .find(|gate| {
+                gate.bosses.iter().any(|boss| {
+                    boss_names.contains(boss)
+                        && gates
+                            .iter()
+                            .filter(|other| other.raid != gate.raid || other.gate != gate.gate)
+                            .all(|other| !other.bosses.contains(boss))
+                })
+            })
+            .or_else(|| gates.first())
+            .copied()
+    }
+
+    // seconds from pull until the gate enrages, a timer for the exact difficulty wins over
+    // one listed without difficulty
+    pub fn enrage(&self, difficulty: &str) -> Option<u32> {
+        let timers = RAID_ENRAGE_DATA
+            .iter()
+            .filter(|timer| timer.raid == self.raid && timer.gate == self.gate);
+        timers
+            .clone()
+            .find(|timer| timer.difficulty.as_deref() == Some(difficulty))
+            .or_else(|| timers.clone().find(|timer| timer.difficulty.is_none()))
+            .map(|timer| timer.seconds)
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RaidEnrage {
+    pub raid: String,
+    pub gate: u32,
+    pub difficulty: Option<String>,
+    pub seconds: u32,
+}
+
+// ordered oldest to newest
+#[rustfmt::skip]
+pub const RAID_GATES: &[RaidGate] = &[
+    RaidGate { raid: "Valtan", gate: 1, display_name: None, bosses: &["Dark Mountain Predator", "Destroyer Lucas", "Leader Lugaru"] },
+    RaidGate { raid: "Valtan", gate: 2, display_name: None, bosses: &["Demon Beast Commander Valtan", "Ravaged Tyrant of Beasts"] },
+    RaidGate { raid: "Vykas", gate: 1, display_name: None, bosses: &["Incubus Morphe", "Nightmarish Morphe"] },
+    RaidGate { raid: "Vykas", gate: 2, display_name: None, bosses: &["Covetous Devourer Vykas"] },
+    RaidGate { raid: "Vykas", gate: 3, display_name: None, bosses: &["Covetous Legion Commander Vykas"] },
+    RaidGate { raid: "Clown", gate: 1, display_name: None, bosses: &["Saydon"] },
+    RaidGate { raid: "Clown", gate: 2, display_name: None, bosses: &["Kakul"] },
+    RaidGate { raid: "Clown", gate: 3, display_name: None, bosses: &["Kakul-Saydon", "Encore-Desiring Kakul-Saydon"] },
+    RaidGate { raid: "Brelshaza", gate: 1, display_name: None, bosses: &["Gehenna Helkasirs"] },
+    RaidGate { raid: "Brelshaza", gate: 2, display_name: None, bosses: &["Prokel", "Prokel's Spiritual Echo", "Ashtarot"] },
+    RaidGate { raid: "Brelshaza", gate: 3, display_name: None, bosses: &["Primordial Nightmare"] },
+    RaidGate { raid: "Brelshaza", gate: 4, display_name: None, bosses: &["Phantom Legion Commander Brelshaza"] },
+    RaidGate { raid: "Brelshaza", gate: 5, display_name: None, bosses: &["Brelshaza, Monarch of Nightmares", "Imagined Primordial Nightmare", "Pseudospace Primordial Nightmare"] },
+    RaidGate { raid: "Kayangel", gate: 1, display_name: None, bosses: &["Tienis"] },
+    RaidGate { raid: "Kayangel", gate: 2, display_name: None, bosses: &["Prunya"] },
+    RaidGate { raid: "Kayangel", gate: 3, display_name: None, bosses: &["Lauriel"] },
+    RaidGate { raid: "Akkan", gate: 1, display_name: None, bosses: &["Griefbringer Maurug", "Evolved Maurug"] },
+    RaidGate { raid: "Akkan", gate: 2, display_name: None, bosses: &["Lord of Degradation Akkan"] },
+    RaidGate { raid: "Akkan", gate: 3, display_name: None, bosses: &["Plague Legion Commander Akkan", "Lord of Kartheon Akkan"] },
+    RaidGate { raid: "Ivory", gate: 1, display_name: None, bosses: &["Kaltaya, the Blooming Chaos"] },
+    RaidGate { raid: "Ivory", gate: 2, display_name: None, bosses: &["Rakathus, the Lurking Arrogance"] },
+    RaidGate { raid: "Ivory", gate: 3, display_name: None, bosses: &["Firehorn, Trampler of Earth"] },
+    RaidGate { raid: "Ivory", gate: 4, display_name: None, bosses: &["Lazaram, the Trailblazer", "Subordinated Vertus", "Subordinated Calventus", "Subordinated Legoros", "Brand of Subordination"] },
+    RaidGate { raid: "Thaemine", gate: 1, display_name: None, bosses: &["Killineza the Dark Worshipper"] },
+    RaidGate { raid: "Thaemine", gate: 2, display_name: None, bosses: &["Valinak, Knight of Darkness", "Valinak, Taboo Usurper", "Valinak, Herald of the End"] },
+    RaidGate { raid: "Thaemine", gate: 3, display_name: None, bosses: &["Thaemine the Lightqueller", "Dark Greatsword"] },
+    RaidGate { raid: "Thaemine", gate: 4, display_name: None, bosses: &["Darkness Legion Commander Thaemine", "Thaemine Prokel", "Thaemine, Conqueror of Stars"] },
+    RaidGate { raid: "Echidna", gate: 1, display_name: None, bosses: &["Red Doom Narkiel", "Agris"] },
+    RaidGate { raid: "Echidna", gate: 2, display_name: None, bosses: &["Echidna", "Covetous Master Echidna", "Desire in Full Bloom, Echidna", "Alcaone, the Twisted Venom", "Agris, the Devouring Bog"] },
+    RaidGate { raid: "Argeos", gate: 1, display_name: Some("Argeos"), bosses: &["Chaos Lightning Dragon Jade"] },
+    RaidGate { raid: "Behemoth", gate: 1, display_name: Some("Behemoth, the Storm Commander"), bosses: &["Behemoth, the Storm Commander", "Despicable Skolakia", "Untrue Crimson Yoho", "Ruthless Lakadroff", "Vicious Argeos"] },
+    RaidGate { raid: "Behemoth", gate: 2, display_name: None, bosses: &["Behemoth, Cruel Storm Slayer"] },
+    RaidGate { raid: "Aegir", gate: 1, display_name: None, bosses: &["Akkan, Lord of Death", "Abyss Monarch Aegir"] },
+    RaidGate { raid: "Aegir", gate: 2, display_name: None, bosses: &["Aegir, the Oppressor", "Pulsating Giant's Heart"] },
+    RaidGate { raid: "Act 2: Brelshaza", gate: 1, display_name: None, bosses: &["Narok the Butcher"] },
+    RaidGate { raid: "Act 2: Brelshaza", gate: 2, display_name: None, bosses: &["Phantom Legion Commander Brelshaza", "Phantom Manifester Brelshaza"] },
+];
+
 pub struct ItemSetInfo {
     pub item_ids: HashMap<u32, ItemSetShort>,
     pub set_names: HashMap<String, ItemSetLevel>,
@@ -1044,6 +1811,27 @@ lazy_static! {
     //         set_names: item_set_names,
     //     }
     // };
+    // bosses are matched to their gates by name once at load, see RaidGate::for_boss
+    pub static ref RAID_BOSS_DATA: HashMap<u32, Vec<&'static RaidGate>> = {
+        let mut gates_by_name: HashMap<&str, Vec<&'static RaidGate>> = HashMap::new();
+        for gate in RAID_GATES {
+            for boss in gate.bosses {
+                gates_by_name.entry(boss).or_default().push(gate);
+            }
+        }
+
+        NPC_DATA
+            .iter()
+            .filter_map(|(npc_id, npc)| {
+                let gates = gates_by_name.get(npc.name.as_deref()?)?;
+                Some((*npc_id, gates.clone()))
+            })
+            .collect()
+    };
+    pub static ref RAID_ENRAGE_DATA: Vec<RaidEnrage> = {
+        let json_str = include_str!("../../meter-data/RaidEnrage.json");
+        serde_json::from_str(json_str).unwrap()
+    };
     pub static ref ESTHER_DATA: Vec<Esther> = {
         let json_str = include_str!("../../meter-data/Esther.json");
         serde_json::from_str(json_str).unwrap()
@@ -1290,6 +2078,11 @@ pub struct LocalInfo {
 pub struct LocalPlayer {
     pub name: String,
     pub count: i32,
+    pub class_id: u32,
+    pub class: String,
+    pub gear_score: f32,
+    // ms timestamp of the last time this character was logged in
+    pub last_seen: i64,
 }
 
 fn default_true() -> bool {
@@ -1300,6 +2093,10 @@ fn default_scale() -> String {
     "1".to_string()
 }
 
+fn default_trash_retention_days() -> u32 {
+    30
+}
+
 fn int_or_string_as_string<'de, D>(deserializer: D) -> Result<String, D::Error>
 where
     D: Deserializer<'de>,
@@ -1,13 +1,17 @@
+use crate::error::AppError;
 use crate::parser::entity_tracker::Entity;
 use crate::parser::models::*;
 use crate::parser::skill_tracker::SkillTracker;
 use crate::parser::stats_api::PlayerStats;
 use crate::parser::status_tracker::StatusEffectDetails;
+use crate::session_assign::{assign_session, get_session_roster};
 use flate2::write::GzEncoder;
 use flate2::Compression;
 use hashbrown::{HashMap, HashSet};
+use log::warn;
 use moka::sync::Cache;
-use rusqlite::{params, Transaction};
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{params, Connection, OptionalExtension, ToSql, Transaction};
 use serde::Serialize;
 use serde_json::json;
 use std::cmp::{max, Ordering, Reverse};
@@ -42,6 +46,78 @@ pub fn update_player_entity(old: &mut EncounterEntity, new: &Entity) {
     old.gear_score = new.gear_level;
 }
 
+// players are keyed by character id and bosses by npc id so entities sharing a display name
+// don't collapse into one. other npcs keep their object id since adds spawned together share
+// an npc id, players use it too until the character id is known
+pub fn entity_key(entity_type: EntityType, id: u64, character_id: u64, npc_id: u32) -> String {
+    match entity_type {
+        EntityType::PLAYER if character_id > 0 => format!("pc:{}", character_id),
+        EntityType::BOSS if npc_id > 0 => format!("npc:{}", npc_id),
+        _ => format!("obj:{:x}", id),
+    }
+}
+
+pub fn get_entity_key(entity: &Entity) -> String {
+    entity_key(
+        entity.entity_type,
+        entity.id,
+        entity.character_id,
+        entity.npc_id,
+    )
+}
+
+fn is_provisional_key(key: &str) -> bool {
+    key.starts_with("obj:")
+}
+
+// find the key an entity is stored under in the encounter
+// entries created before the stable id was known are moved to the stable key
+pub fn resolve_entity_key(encounter: &mut Encounter, entity: &Entity) -> String {
+    let key = get_entity_key(entity);
+    if encounter.entities.contains_key(&key) {
+        return key;
+    }
+
+    let existing = match encounter.entity_key(&entity.name) {
+        Some(existing) => existing,
+        None => return key,
+    };
+    // only players seen before their character id, and npcs promoted to boss, are merged by
+    // name. other npcs sharing a name are separate adds
+    let same_entity = encounter
+        .entities
+        .get(&existing)
+        .is_some_and(|e| match entity.entity_type {
+            EntityType::PLAYER => e.entity_type == EntityType::PLAYER,
+            EntityType::BOSS => e.entity_type != EntityType::PLAYER && e.npc_id == entity.npc_id,
+            _ => false,
+        });
+    if !same_entity {
+        return key;
+    }
+
+    if is_provisional_key(&key) {
+        existing
+    } else if is_provisional_key(&existing) {
+        if let Some(e) = encounter.entities.remove(&existing) {
+            encounter.insert_entity(key.clone(), e);
+        }
+        key
+    } else {
+        key
+    }
+}
+
+pub fn find_encounter_entity<'a>(
+    encounter: &'a Encounter,
+    entity: &Entity,
+) -> Option<&'a EncounterEntity> {
+    encounter
+        .entities
+        .get(&get_entity_key(entity))
+        .or_else(|| encounter.entity_by_name(&entity.name))
+}
+
 pub fn is_support_class_id(class_id: u32) -> bool {
     class_id == 105 || class_id == 204 || class_id == 602
 }
@@ -783,6 +859,33 @@ pub fn check_tripod_level_change(before: Option<TripodLevel>, after: Option<Trip
     before != after
 }
 
+fn get_target_names(encounter: &Encounter) -> HashMap<u32, String> {
+    let mut target_names: HashMap<u32, String> = HashMap::new();
+    for npc_id in encounter
+        .entities
+        .values()
+        .flat_map(|e| e.damage_stats.damage_by_target.keys())
+    {
+        if target_names.contains_key(npc_id) {
+            continue;
+        }
+        let name = NPC_DATA
+            .get(npc_id)
+            .and_then(|npc| npc.name.clone())
+            .or_else(|| {
+                encounter
+                    .entities
+                    .values()
+                    .find(|e| e.npc_id == *npc_id)
+                    .map(|e| e.name.clone())
+            })
+            .unwrap_or_else(|| npc_id.to_string());
+        target_names.insert(*npc_id, name);
+    }
+
+    target_names
+}
+
 const WINDOW_MS: i64 = 5_000;
 const WINDOW_S: i64 = 5;
 
@@ -807,30 +910,8 @@ pub fn insert_data(
     rdps_valid: bool,
     manual: bool,
     skill_cast_log: HashMap<u64, HashMap<u32, BTreeMap<i64, SkillCast>>>,
-) -> i64 {
-    let mut encounter_stmt = tx
-        .prepare_cached(
-            "
-    INSERT INTO encounter (
-        last_combat_packet,
-        total_damage_dealt,
-        top_damage_dealt,
-        total_damage_taken,
-        top_damage_taken,
-        dps,
-        buffs,
-        debuffs,
-        total_shielding,
-        total_effective_shielding,
-        applied_shield_buffs,
-        misc,
-        version,
-        boss_hp_log,
-        stagger_log
-    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
-        )
-        .expect("failed to prepare encounter statement");
-
+    wipe_report: Option<WipeReport>,
+) -> Result<(i64, Vec<PersonalBest>), AppError> {
     encounter.duration = encounter.last_combat_packet - encounter.fight_start;
     let duration_seconds = max(encounter.duration / 1000, 1);
     encounter.encounter_damage_stats.dps =
@@ -859,6 +940,8 @@ pub fn insert_data(
         },
         ntp_fight_start: Some(ntp_fight_start),
         manual_save: Some(manual),
+        target_names: Some(get_target_names(&encounter)),
+        wipe_report,
         ..Default::default()
     };
 
@@ -900,8 +983,9 @@ pub fn insert_data(
     let compressed_debuffs = compress_json(&encounter.encounter_damage_stats.debuffs);
     let compressed_shields = compress_json(&encounter.encounter_damage_stats.applied_shield_buffs);
 
-    encounter_stmt
-        .execute(params![
+    let last_insert_id = insert_encounter_row(
+        tx,
+        params![
             encounter.last_combat_packet,
             encounter.encounter_damage_stats.total_damage_dealt,
             encounter.encounter_damage_stats.top_damage_dealt,
@@ -917,43 +1001,13 @@ pub fn insert_data(
             DB_VERSION,
             compressed_boss_hp,
             json!(stagger_stats),
-        ])
-        .expect("failed to insert encounter");
-
-    let last_insert_id = tx.last_insert_rowid();
-
-    let mut entity_stmt = tx
-        .prepare_cached(
-            "
-    INSERT INTO entity (
-        name,
-        encounter_id,
-        npc_id,
-        entity_type,
-        class_id,
-        class,
-        gear_score,
-        current_hp,
-        max_hp,
-        is_dead,
-        skills,
-        damage_stats,
-        skill_stats,
-        dps,
-        character_id,
-        engravings,
-        gear_hash,
-        ark_passive_active,
-        spec,
-        ark_passive_data
-    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)",
-        )
-        .expect("failed to prepare entity statement");
+        ],
+    )?;
 
     let fight_start = encounter.fight_start;
     let fight_end = encounter.last_combat_packet;
 
-    for (_key, entity) in encounter.entities.iter_mut().filter(|(_, e)| {
+    for (key, entity) in encounter.entities.iter_mut().filter(|(_, e)| {
         ((e.entity_type == EntityType::PLAYER && e.class_id != 0 && e.max_hp > 0)
             || e.name == encounter.local_player
             || e.entity_type == EntityType::ESTHER
@@ -1219,8 +1273,9 @@ pub fn insert_data(
         let compressed_skills = compress_json(&entity.skills);
         let compressed_damage_stats = compress_json(&entity.damage_stats);
 
-        entity_stmt
-            .execute(params![
+        insert_entity_row(
+            tx,
+            params![
                 entity.name,
                 last_insert_id,
                 entity.npc_id,
@@ -1240,9 +1295,10 @@ pub fn insert_data(
                 entity.gear_hash,
                 entity.ark_passive_active,
                 entity.spec,
-                json!(entity.ark_passive_data)
-            ])
-            .expect("failed to insert entity");
+                json!(entity.ark_passive_data),
+                key
+            ],
+        )?;
     }
 
     let mut players = encounter
@@ -1266,9 +1322,114 @@ pub fn insert_data(
         .collect::<Vec<_>>()
         .join(",");
 
-    let mut encounter_preview_stmt = tx
-        .prepare_cached(
-            "
+    insert_encounter_preview_row(
+        tx,
+        params![
+            last_insert_id,
+            encounter.fight_start,
+            encounter.current_boss_name,
+            encounter.duration,
+            preview_players,
+            raid_difficulty,
+            encounter.local_player,
+            local_player_dps,
+            raid_clear,
+            encounter.boss_only_damage,
+            encounter.raid,
+            encounter.gate
+        ],
+    )?;
+
+    let roster = get_session_roster(Some(&misc), &preview_players);
+    if let Err(e) = assign_session(tx, last_insert_id, encounter.fight_start, &roster) {
+        warn!("could not assign raid session: {}", e);
+    }
+
+    // a failed record update should not cost the encounter itself
+    let personal_bests = if raid_difficulty.is_empty() || encounter.current_boss_name.is_empty() {
+        vec![]
+    } else {
+        update_personal_bests(
+            tx,
+            last_insert_id,
+            &encounter,
+            &raid_difficulty,
+            raid_clear,
+            rdps_valid,
+        )
+        .unwrap_or_else(|e| {
+            warn!("could not update personal bests: {}", e);
+            vec![]
+        })
+    };
+
+    Ok((last_insert_id, personal_bests))
+}
+
+// row writers shared by insert_data and imports, params follow the column order
+fn insert_encounter_row(tx: &Transaction, params: &[&dyn ToSql]) -> Result<i64, rusqlite::Error> {
+    let mut stmt = tx.prepare_cached(
+        "
+    INSERT INTO encounter (
+        last_combat_packet,
+        total_damage_dealt,
+        top_damage_dealt,
+        total_damage_taken,
+        top_damage_taken,
+        dps,
+        buffs,
+        debuffs,
+        total_shielding,
+        total_effective_shielding,
+        applied_shield_buffs,
+        misc,
+        version,
+        boss_hp_log,
+        stagger_log
+    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+    )?;
+    stmt.execute(params)?;
+    Ok(tx.last_insert_rowid())
+}
+
+fn insert_entity_row(tx: &Transaction, params: &[&dyn ToSql]) -> Result<(), rusqlite::Error> {
+    let mut stmt = tx.prepare_cached(
+        "
+    INSERT INTO entity (
+        name,
+        encounter_id,
+        npc_id,
+        entity_type,
+        class_id,
+        class,
+        gear_score,
+        current_hp,
+        max_hp,
+        is_dead,
+        skills,
+        damage_stats,
+        skill_stats,
+        dps,
+        character_id,
+        engravings,
+        gear_hash,
+        ark_passive_active,
+        spec,
+        ark_passive_data,
+        entity_key
+    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21)",
+    )?;
+    stmt.execute(params)?;
+    Ok(())
+}
+
+// search rows are added by the encounter_preview triggers
+fn insert_encounter_preview_row(
+    tx: &Transaction,
+    params: &[&dyn ToSql],
+) -> Result<(), rusqlite::Error> {
+    let mut stmt = tx.prepare_cached(
+        "
     INSERT INTO encounter_preview (
         id,
         fight_start,
@@ -1279,26 +1440,383 @@ pub fn insert_data(
         local_player,
         my_dps,
         cleared,
-        boss_only_damage
-    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        boss_only_damage,
+        raid,
+        gate
+    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+    )?;
+    stmt.execute(params)?;
+    Ok(())
+}
+
+// raw bytes of a json column, gzipped since 1.13.5 and plain text before that
+fn read_column_bytes(value: ValueRef) -> Option<Vec<u8>> {
+    match value {
+        ValueRef::Blob(bytes) | ValueRef::Text(bytes) => Some(bytes.to_vec()),
+        _ => None,
+    }
+}
+
+fn to_column_value(bytes: &Option<Vec<u8>>, compressed: bool) -> Value {
+    match bytes {
+        Some(bytes) if compressed => Value::Blob(bytes.clone()),
+        Some(bytes) => Value::Text(String::from_utf8_lossy(bytes).into_owned()),
+        None => Value::Null,
+    }
+}
+
+// copies the stored rows as is, json blobs stay compressed
+pub fn export_encounter_data(
+    conn: &Connection,
+    id: &str,
+    meter_version: String,
+) -> Result<EncounterExport, rusqlite::Error> {
+    let mut encounter_stmt = conn.prepare_cached(
+        "
+    SELECT e.last_combat_packet,
+        e.total_damage_dealt,
+        e.top_damage_dealt,
+        e.total_damage_taken,
+        e.top_damage_taken,
+        e.dps,
+        e.buffs,
+        e.debuffs,
+        e.total_shielding,
+        e.total_effective_shielding,
+        e.applied_shield_buffs,
+        e.misc,
+        e.version,
+        e.boss_hp_log,
+        e.stagger_log,
+        ep.fight_start,
+        ep.current_boss,
+        ep.duration,
+        ep.players,
+        ep.difficulty,
+        ep.local_player,
+        ep.my_dps,
+        ep.cleared,
+        ep.boss_only_damage,
+        ep.raid,
+        ep.gate
+    FROM encounter e
+    JOIN encounter_preview ep USING (id)
+    WHERE e.id = ?
+    ",
+    )?;
+    let (compressed, encounter) = encounter_stmt.query_row(params![id], |row| {
+        let compressed = matches!(row.get_ref(6)?, ValueRef::Blob(_));
+        Ok((
+            compressed,
+            ExportedEncounter {
+                last_combat_packet: row.get(0)?,
+                total_damage_dealt: row.get(1).unwrap_or_default(),
+                top_damage_dealt: row.get(2).unwrap_or_default(),
+                total_damage_taken: row.get(3).unwrap_or_default(),
+                top_damage_taken: row.get(4).unwrap_or_default(),
+                dps: row.get(5).unwrap_or_default(),
+                buffs: read_column_bytes(row.get_ref(6)?),
+                debuffs: read_column_bytes(row.get_ref(7)?),
+                total_shielding: row.get(8).unwrap_or_default(),
+                total_effective_shielding: row.get(9).unwrap_or_default(),
+                applied_shield_buffs: read_column_bytes(row.get_ref(10)?),
+                misc: row.get(11).unwrap_or_default(),
+                version: row.get(12).unwrap_or_default(),
+                boss_hp_log: read_column_bytes(row.get_ref(13)?),
+                stagger_log: row.get(14).unwrap_or_default(),
+                fight_start: row.get(15)?,
+                current_boss: row.get(16)?,
+                duration: row.get(17)?,
+                players: row.get(18).unwrap_or_default(),
+                difficulty: row.get(19).unwrap_or_default(),
+                local_player: row.get(20).unwrap_or_default(),
+                my_dps: row.get(21).unwrap_or_default(),
+                cleared: row.get(22).unwrap_or_default(),
+                boss_only_damage: row.get(23).unwrap_or_default(),
+                raid: row.get(24).unwrap_or_default(),
+                gate: row.get(25).unwrap_or_default(),
+            },
+        ))
+    })?;
+
+    let entity_table = if crate::archive::is_archived(conn, id) {
+        "archive.entity"
+    } else {
+        "entity"
+    };
+    let mut entity_stmt = conn.prepare_cached(&format!(
+        "
+    SELECT name,
+        npc_id,
+        entity_type,
+        class_id,
+        class,
+        gear_score,
+        current_hp,
+        max_hp,
+        is_dead,
+        skills,
+        damage_stats,
+        skill_stats,
+        dps,
+        character_id,
+        engravings,
+        gear_hash,
+        ark_passive_active,
+        spec,
+        ark_passive_data,
+        entity_key
+    FROM {}
+    WHERE encounter_id = ?
+    ",
+        entity_table
+    ))?;
+    let entities = entity_stmt
+        .query_map(params![id], |row| {
+            Ok(ExportedEntity {
+                name: row.get(0)?,
+                npc_id: row.get(1).unwrap_or_default(),
+                entity_type: row.get(2).unwrap_or_default(),
+                class_id: row.get(3).unwrap_or_default(),
+                class: row.get(4).unwrap_or_default(),
+                gear_score: row.get(5).unwrap_or_default(),
+                current_hp: row.get(6).unwrap_or_default(),
+                max_hp: row.get(7).unwrap_or_default(),
+                is_dead: row.get(8).unwrap_or_default(),
+                skills: read_column_bytes(row.get_ref(9)?),
+                damage_stats: read_column_bytes(row.get_ref(10)?),
+                skill_stats: row.get(11).unwrap_or_default(),
+                dps: row.get(12).unwrap_or_default(),
+                character_id: row.get(13).unwrap_or_default(),
+                engravings: row.get(14).unwrap_or_default(),
+                gear_hash: row.get(15).unwrap_or_default(),
+                ark_passive_active: row.get(16).unwrap_or_default(),
+                spec: row.get(17).unwrap_or_default(),
+                ark_passive_data: row.get(18).unwrap_or_default(),
+                entity_key: row.get(19)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(EncounterExport {
+        version: ENCOUNTER_EXPORT_VERSION,
+        meter_version,
+        compressed,
+        encounter,
+        entities,
+    })
+}
+
+// imported logs are someone else's, so they stay out of sessions and personal bests
+// returns the existing id if the encounter was already imported
+pub fn import_encounter_data(
+    tx: &Transaction,
+    export: &EncounterExport,
+) -> Result<i64, rusqlite::Error> {
+    let encounter = &export.encounter;
+    let existing = tx
+        .query_row(
+            "
+    SELECT id
+    FROM encounter_preview
+    WHERE fight_start = ? AND current_boss = ? AND local_player = ?
+    ",
+            params![
+                encounter.fight_start,
+                encounter.current_boss,
+                encounter.local_player
+            ],
+            |row| row.get(0),
         )
-        .expect("failed to prepare encounter preview statement");
-    encounter_preview_stmt
-        .execute(params![
-            last_insert_id,
+        .optional()?;
+    if let Some(id) = existing {
+        return Ok(id);
+    }
+
+    let compressed = export.compressed;
+    let encounter_id = insert_encounter_row(
+        tx,
+        params![
+            encounter.last_combat_packet,
+            encounter.total_damage_dealt,
+            encounter.top_damage_dealt,
+            encounter.total_damage_taken,
+            encounter.top_damage_taken,
+            encounter.dps,
+            to_column_value(&encounter.buffs, compressed),
+            to_column_value(&encounter.debuffs, compressed),
+            encounter.total_shielding,
+            encounter.total_effective_shielding,
+            to_column_value(&encounter.applied_shield_buffs, compressed),
+            encounter.misc,
+            encounter.version,
+            to_column_value(&encounter.boss_hp_log, compressed),
+            encounter.stagger_log,
+        ],
+    )?;
+
+    for entity in export.entities.iter() {
+        insert_entity_row(
+            tx,
+            params![
+                entity.name,
+                encounter_id,
+                entity.npc_id,
+                entity.entity_type,
+                entity.class_id,
+                entity.class,
+                entity.gear_score,
+                entity.current_hp,
+                entity.max_hp,
+                entity.is_dead,
+                to_column_value(&entity.skills, compressed),
+                to_column_value(&entity.damage_stats, compressed),
+                entity.skill_stats,
+                entity.dps,
+                entity.character_id,
+                entity.engravings,
+                entity.gear_hash,
+                entity.ark_passive_active,
+                entity.spec,
+                entity.ark_passive_data,
+                entity.entity_key
+            ],
+        )?;
+    }
+
+    insert_encounter_preview_row(
+        tx,
+        params![
+            encounter_id,
             encounter.fight_start,
-            encounter.current_boss_name,
+            encounter.current_boss,
             encounter.duration,
-            preview_players,
-            raid_difficulty,
+            encounter.players,
+            encounter.difficulty,
             encounter.local_player,
-            local_player_dps,
-            raid_clear,
-            encounter.boss_only_damage
-        ])
-        .expect("failed to insert encounter preview");
+            encounter.my_dps,
+            encounter.cleared,
+            encounter.boss_only_damage,
+            encounter.raid,
+            encounter.gate
+        ],
+    )?;
+
+    Ok(encounter_id)
+}
+
+// compares the local player against their stored records for this boss and difficulty,
+// returns the records that were beaten
+fn update_personal_bests(
+    tx: &Transaction,
+    encounter_id: i64,
+    encounter: &Encounter,
+    difficulty: &str,
+    raid_clear: bool,
+    rdps_valid: bool,
+) -> Result<Vec<PersonalBest>, rusqlite::Error> {
+    let Some(player) = encounter
+        .entity_by_name(&encounter.local_player)
+        .filter(|e| is_valid_player(e))
+    else {
+        return Ok(vec![]);
+    };
+
+    let duration_seconds = max(encounter.duration / 1000, 1);
+    let mut candidates: Vec<(&str, i64, Option<u32>)> =
+        vec![("dps", player.damage_stats.dps, None)];
+    if rdps_valid && player.damage_stats.rdps_damage_given > 0 {
+        candidates.push((
+            "rdps",
+            player.damage_stats.rdps_damage_given / duration_seconds,
+            None,
+        ));
+    }
+    if raid_clear {
+        candidates.push(("clear_time", encounter.duration, None));
+    }
+    if let Some(skill) = player.skills.values().max_by_key(|s| s.max_damage) {
+        candidates.push(("max_hit", skill.max_damage, Some(skill.id)));
+    }
+
+    let mut select_stmt = tx.prepare_cached(
+        "
+    SELECT value
+    FROM personal_best
+    WHERE name = ? AND class_id = ? AND boss = ? AND difficulty = ? AND kind = ?",
+    )?;
+    let mut upsert_stmt = tx.prepare_cached(
+        "
+    INSERT OR REPLACE INTO personal_best (
+        name,
+        class_id,
+        class,
+        boss,
+        difficulty,
+        kind,
+        value,
+        skill_id,
+        encounter_id,
+        fight_start
+    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+    )?;
+
+    let mut personal_bests = vec![];
+    for (kind, value, skill_id) in candidates {
+        if value <= 0 {
+            continue;
+        }
+
+        let previous: Option<i64> = select_stmt
+            .query_row(
+                params![
+                    player.name,
+                    player.class_id,
+                    encounter.current_boss_name,
+                    difficulty,
+                    kind
+                ],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let is_better = match previous {
+            Some(previous) if kind == "clear_time" => value < previous,
+            Some(previous) => value > previous,
+            None => true,
+        };
+        if !is_better {
+            continue;
+        }
+
+        upsert_stmt.execute(params![
+            player.name,
+            player.class_id,
+            player.class,
+            encounter.current_boss_name,
+            difficulty,
+            kind,
+            value,
+            skill_id,
+            encounter_id,
+            encounter.fight_start
+        ])?;
+
+        personal_bests.push(PersonalBest {
+            name: player.name.clone(),
+            class_id: player.class_id,
+            class: player.class.clone(),
+            boss: encounter.current_boss_name.clone(),
+            difficulty: difficulty.to_string(),
+            kind: kind.to_string(),
+            value,
+            previous,
+            skill_id,
+            encounter_id,
+            fight_start: encounter.fight_start,
+        });
+    }
 
-    last_insert_id
+    Ok(personal_bests)
 }
 
 pub fn map_status_effect(se: &StatusEffectDetails, custom_id_map: &mut HashMap<u32, u32>) -> u32 {
@@ -1342,17 +1860,6 @@ where
     e.finish().expect("unable to compress json")
 }
 
-pub fn update_current_boss_name(boss_name: &str) -> String {
-    match boss_name {
-        "Chaos Lightning Dragon Jade" => "Argeos",
-        "Vicious Argeos" | "Ruthless Lakadroff" | "Untrue Crimson Yoho" | "Despicable Skolakia" => {
-            "Behemoth, the Storm Commander"
-        }
-        _ => boss_name,
-    }
-    .to_string()
-}
-
 fn get_player_spec(player: &EncounterEntity, buffs: &HashMap<u32, StatusEffect>) -> String {
     if player.skills.len() < 8 {
         return "Unknown".to_string();
@@ -1641,3 +2148,207 @@ fn get_spec_from_ark_passive(node: &ArkPassiveNode) -> String {
     }
     .to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(id: u64, entity_type: EntityType, character_id: u64, npc_id: u32) -> Entity {
+        Entity {
+            id,
+            entity_type,
+            name: "Name".to_string(),
+            character_id,
+            npc_id,
+            ..Default::default()
+        }
+    }
+
+    fn insert(encounter: &mut Encounter, entity: &Entity) -> String {
+        let key = resolve_entity_key(encounter, entity);
+        encounter.insert_entity(
+            key.clone(),
+            EncounterEntity {
+                id: entity.id,
+                character_id: entity.character_id,
+                npc_id: entity.npc_id,
+                name: entity.name.clone(),
+                entity_type: entity.entity_type,
+                ..Default::default()
+            },
+        );
+        key
+    }
+
+    #[test]
+    fn keys_players_and_bosses_by_stable_id() {
+        assert_eq!(entity_key(EntityType::PLAYER, 0x10, 42, 0), "pc:42");
+        assert_eq!(entity_key(EntityType::PLAYER, 0x10, 0, 0), "obj:10");
+        assert_eq!(entity_key(EntityType::BOSS, 0x20, 0, 480010), "npc:480010");
+        assert_eq!(entity_key(EntityType::BOSS, 0x20, 0, 0), "obj:20");
+    }
+
+    #[test]
+    fn keys_other_npcs_by_object_id() {
+        assert_eq!(entity_key(EntityType::MONSTER, 0x30, 0, 480010), "obj:30");
+        assert_ne!(
+            entity_key(EntityType::MONSTER, 0x30, 0, 1),
+            entity_key(EntityType::MONSTER, 0x31, 0, 1)
+        );
+    }
+
+    #[test]
+    fn moves_player_to_character_id_once_known() {
+        let mut encounter = Encounter::default();
+        assert_eq!(
+            insert(&mut encounter, &entity(0x10, EntityType::PLAYER, 0, 0)),
+            "obj:10"
+        );
+
+        let key = resolve_entity_key(&mut encounter, &entity(0x11, EntityType::PLAYER, 42, 0));
+        assert_eq!(key, "pc:42");
+        assert!(encounter.entities.contains_key("pc:42"));
+        assert!(!encounter.entities.contains_key("obj:10"));
+    }
+
+    #[test]
+    fn keeps_adds_sharing_a_name_apart() {
+        let mut encounter = Encounter::default();
+        insert(&mut encounter, &entity(0x30, EntityType::MONSTER, 0, 1));
+        insert(&mut encounter, &entity(0x31, EntityType::MONSTER, 0, 1));
+
+        assert_eq!(encounter.entities.len(), 2);
+    }
+
+    #[test]
+    fn merges_npc_promoted_to_boss() {
+        let mut encounter = Encounter::default();
+        insert(
+            &mut encounter,
+            &entity(0x40, EntityType::MONSTER, 0, 480010),
+        );
+
+        let key = resolve_entity_key(&mut encounter, &entity(0x40, EntityType::BOSS, 0, 480010));
+        assert_eq!(key, "npc:480010");
+        assert_eq!(encounter.entities.len(), 1);
+    }
+
+    fn export_db() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::migrations::run_migrations(&mut conn, &std::env::temp_dir(), false).unwrap();
+        conn
+    }
+
+    fn exported_encounter() -> EncounterExport {
+        EncounterExport {
+            version: ENCOUNTER_EXPORT_VERSION,
+            meter_version: "1.0.0".to_string(),
+            compressed: true,
+            encounter: ExportedEncounter {
+                last_combat_packet: 1_700_000_300_000,
+                total_damage_dealt: 1000,
+                dps: 10,
+                buffs: Some(compress_json(&json!({"1": {"id": 1}}))),
+                debuffs: Some(compress_json(&json!({}))),
+                applied_shield_buffs: Some(compress_json(&json!({}))),
+                boss_hp_log: Some(compress_json(&json!({"Boss": []}))),
+                fight_start: 1_700_000_000_000,
+                current_boss: "Boss".to_string(),
+                duration: 300_000,
+                players: "a".to_string(),
+                local_player: "a".to_string(),
+                cleared: true,
+                ..Default::default()
+            },
+            entities: vec![ExportedEntity {
+                name: "a".to_string(),
+                entity_type: "PLAYER".to_string(),
+                class_id: 102,
+                skills: Some(compress_json(&json!({"1": {"id": 1}}))),
+                damage_stats: Some(compress_json(&json!({"damageDealt": 1000}))),
+                dps: 10,
+                character_id: 42,
+                entity_key: "pc:42".to_string(),
+                ..Default::default()
+            }],
+        }
+    }
+
+    fn import(conn: &mut Connection, export: &EncounterExport) -> i64 {
+        let tx = conn.transaction().unwrap();
+        let id = import_encounter_data(&tx, export).unwrap();
+        tx.commit().unwrap();
+        id
+    }
+
+    fn export(conn: &Connection, id: i64) -> EncounterExport {
+        export_encounter_data(conn, &id.to_string(), "1.0.0".to_string()).unwrap()
+    }
+
+    #[test]
+    fn export_round_trips_through_json() {
+        let mut conn = export_db();
+        let id = import(&mut conn, &exported_encounter());
+        let exported = export(&conn, id);
+        assert!(exported.compressed);
+
+        let json = serde_json::to_string(&exported).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["version"], 2);
+        assert!(value["encounter"]["buffs"].is_string());
+        assert!(value["entities"][0]["damageStats"].is_string());
+
+        let mut other = export_db();
+        let imported_id = import(&mut other, &serde_json::from_str(&json).unwrap());
+        let imported = export(&other, imported_id);
+        assert_eq!(
+            serde_json::to_value(&imported).unwrap(),
+            serde_json::to_value(&exported).unwrap()
+        );
+    }
+
+    #[test]
+    fn imports_version_1_byte_arrays() {
+        let original = exported_encounter();
+        let mut value = serde_json::to_value(&original).unwrap();
+        value["version"] = json!(1);
+        // version 1 wrote the blob columns as plain byte arrays
+        let encounter = &original.encounter;
+        value["encounter"]["buffs"] = json!(encounter.buffs);
+        value["encounter"]["debuffs"] = json!(encounter.debuffs);
+        value["encounter"]["appliedShieldBuffs"] = json!(encounter.applied_shield_buffs);
+        value["encounter"]["bossHpLog"] = json!(encounter.boss_hp_log);
+        value["entities"][0]["skills"] = json!(original.entities[0].skills);
+        value["entities"][0]["damageStats"] = json!(original.entities[0].damage_stats);
+        assert!(value["encounter"]["buffs"].is_array());
+
+        let mut conn = export_db();
+        let id = import(&mut conn, &serde_json::from_value(value).unwrap());
+        let exported = export(&conn, id);
+        assert_eq!(exported.encounter.buffs, original.encounter.buffs);
+        assert_eq!(
+            exported.encounter.boss_hp_log,
+            original.encounter.boss_hp_log
+        );
+        assert_eq!(exported.entities[0].skills, original.entities[0].skills);
+        assert_eq!(
+            exported.entities[0].damage_stats,
+            original.entities[0].damage_stats
+        );
+    }
+
+    #[test]
+    fn importing_twice_returns_the_existing_encounter() {
+        let mut conn = export_db();
+        let encounter = exported_encounter();
+        let id = import(&mut conn, &encounter);
+
+        assert_eq!(import(&mut conn, &encounter), id);
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM encounter_preview", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+}
@@ -0,0 +1,391 @@
+use crate::analysis::{get_phase_boss, percent};
+use crate::parser::models::*;
+use crate::parser::utils::get_skill_name;
+use hashbrown::{HashMap, HashSet};
+use std::collections::VecDeque;
+
+// buff uptime and counters are looked at over this window before the wipe
+const RECENT_WINDOW_MS: i64 = 30_000;
+// only blame a death on a hit the player took shortly before dying
+const KILLING_HIT_WINDOW_MS: i64 = 5_000;
+// a counter lands while the boss is still in the skill it started
+const COUNTER_WINDOW_MS: i64 = 5_000;
+
+// names are resolved when the report is built, hits only keep object and skill ids
+#[derive(Debug, Clone, Copy)]
+struct LastHit {
+    timestamp: i64,
+    skill_id: u32,
+    source_id: u64,
+}
+
+#[derive(Debug)]
+struct RecentHit {
+    timestamp: i64,
+    player_id: u64,
+    damage: i64,
+    buffed_by_support: bool,
+    buffed_by_identity: bool,
+    debuffed_by_support: bool,
+}
+
+#[derive(Debug)]
+struct Death {
+    name: String,
+    class_id: u32,
+    timestamp: i64,
+    last_hit: Option<LastHit>,
+}
+
+#[derive(Debug)]
+struct BossCast {
+    timestamp: i64,
+    skill_id: u32,
+    countered: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct WipeTracker {
+    deaths: Vec<Death>,
+    last_hits: HashMap<u64, LastHit>,
+    recent_hits: VecDeque<RecentHit>,
+    counters: VecDeque<i64>,
+    boss_casts: Vec<BossCast>,
+    // there is no data on which boss skills can be countered, a skill counts as counterable
+    // once a counter landed during it
+    counterable_skills: HashSet<u32>,
+}
+
+impl WipeTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // start a new pull, counterable skills seen in earlier pulls are kept
+    pub fn reset(&mut self) {
+        *self = Self {
+            counterable_skills: std::mem::take(&mut self.counterable_skills),
+            ..Self::default()
+        };
+    }
+
+    pub fn on_player_hit(
+        &mut self,
+        player_id: u64,
+        timestamp: i64,
+        damage: i64,
+        buffed_by_support: bool,
+        buffed_by_identity: bool,
+        debuffed_by_support: bool,
+    ) {
+        self.recent_hits.push_back(RecentHit {
+            timestamp,
+            player_id,
+            damage,
+            buffed_by_support,
+            buffed_by_identity,
+            debuffed_by_support,
+        });
+        while self
+            .recent_hits
+            .front()
+            .is_some_and(|hit| timestamp - hit.timestamp > RECENT_WINDOW_MS)
+        {
+            self.recent_hits.pop_front();
+        }
+    }
+
+    // damage taken by a player from a non player source
+    pub fn on_hit_taken(&mut self, target_id: u64, source_id: u64, skill_id: u32, timestamp: i64) {
+        self.last_hits.insert(
+            target_id,
+            LastHit {
+                timestamp,
+                skill_id,
+                source_id,
+            },
+        );
+    }
+
+    pub fn on_death(&mut self, player_id: u64, name: &str, class_id: u32, timestamp: i64) {
+        let last_hit = self
+            .last_hits
+            .get(&player_id)
+            .filter(|hit| timestamp - hit.timestamp <= KILLING_HIT_WINDOW_MS)
+            .copied();
+        self.deaths.push(Death {
+            name: name.to_string(),
+            class_id,
+            timestamp,
+            last_hit,
+        });
+    }
+
+    pub fn on_boss_skill(&mut self, skill_id: u32, timestamp: i64) {
+        self.boss_casts.push(BossCast {
+            timestamp,
+            skill_id,
+            countered: false,
+        });
+    }
+
+    pub fn on_counter(&mut self, timestamp: i64) {
+        self.counters.push_back(timestamp);
+        while self
+            .counters
+            .front()
+            .is_some_and(|t| timestamp - t > RECENT_WINDOW_MS)
+        {
+            self.counters.pop_front();
+        }
+
+        if let Some(cast) = self
+            .boss_casts
+            .iter_mut()
+            .rev()
+            .take_while(|cast| timestamp - cast.timestamp <= COUNTER_WINDOW_MS)
+            .find(|cast| cast.timestamp <= timestamp)
+        {
+            cast.countered = true;
+            self.counterable_skills.insert(cast.skill_id);
+        }
+    }
+
+    pub fn build_report(
+        &self,
+        encounter: &Encounter,
+        boss_hp_log: &HashMap<String, Vec<BossHpLog>>,
+        stagger_check_missed: Option<bool>,
+        timestamp: i64,
+    ) -> WipeReport {
+        let mut report = WipeReport {
+            boss_name: encounter.current_boss_name.clone(),
+            hp_percent: 100.0,
+            phase: get_phase_boss(boss_hp_log, &encounter.current_boss_name)
+                .map_or(0, |(phase, _, _)| phase),
+            wipe_time: encounter.last_combat_packet - encounter.fight_start,
+            stagger_check_missed,
+            recent_counters: self
+                .counters
+                .iter()
+                .filter(|t| timestamp - **t <= RECENT_WINDOW_MS)
+                .count() as i64,
+            ..Default::default()
+        };
+
+        if let Some(boss) = encounter.entity_by_name(&encounter.current_boss_name) {
+            report.boss_hp = boss.current_hp.max(0) + boss.current_shield as i64;
+            report.boss_max_hp = boss.max_hp;
+            if boss.max_hp > 0 {
+                report.hp_percent = percent(report.boss_hp, boss.max_hp);
+            }
+        }
+
+        for death in self.deaths.iter() {
+            let last_hit = death.last_hit.map(|hit| {
+                let source = encounter
+                    .entities
+                    .values()
+                    .find(|e| e.id == hit.source_id)
+                    .map(|e| e.name.clone());
+                (hit.skill_id, get_skill_name(&hit.skill_id), source)
+            });
+            report.deaths.push(WipeDeath {
+                name: death.name.clone(),
+                class_id: death.class_id,
+                time: death.timestamp - encounter.fight_start,
+                skill_id: last_hit.as_ref().map(|(skill_id, _, _)| *skill_id),
+                skill_name: last_hit
+                    .as_ref()
+                    .map(|(_, skill_name, _)| skill_name.clone()),
+                source: last_hit.as_ref().and_then(|(_, _, source)| source.clone()),
+            });
+
+            let Some((skill_id, skill_name, Some(source))) = last_hit else {
+                continue;
+            };
+            match report
+                .killing_skills
+                .iter_mut()
+                .find(|s| s.source == source && s.skill_id == skill_id)
+            {
+                Some(killing_skill) => killing_skill.deaths += 1,
+                None => report.killing_skills.push(WipeKillingSkill {
+                    source,
+                    skill_id,
+                    skill_name,
+                    deaths: 1,
+                }),
+            }
+        }
+        // death packets can arrive out of order around a wipe
+        report.deaths.sort_by_key(|death| death.time);
+        report
+            .killing_skills
+            .sort_by(|a, b| b.deaths.cmp(&a.deaths));
+
+        // (damage, buffed by support, buffed by identity, debuffed by support)
+        let mut uptimes: HashMap<u64, (i64, i64, i64, i64)> = HashMap::new();
+        for hit in self
+            .recent_hits
+            .iter()
+            .filter(|hit| timestamp - hit.timestamp <= RECENT_WINDOW_MS)
+        {
+            let uptime = uptimes.entry(hit.player_id).or_default();
+            uptime.0 += hit.damage;
+            if hit.buffed_by_support {
+                uptime.1 += hit.damage;
+            }
+            if hit.buffed_by_identity {
+                uptime.2 += hit.damage;
+            }
+            if hit.debuffed_by_support {
+                uptime.3 += hit.damage;
+            }
+        }
+        report.buff_uptime = uptimes
+            .into_iter()
+            .filter_map(|(player_id, (damage, support, identity, debuff))| {
+                let player = encounter
+                    .entities
+                    .values()
+                    .find(|e| e.id == player_id && e.entity_type == EntityType::PLAYER)?;
+                Some(WipeBuffUptime {
+                    name: player.name.clone(),
+                    class_id: player.class_id,
+                    damage,
+                    buffed_by_support: percent(support, damage),
+                    buffed_by_identity: percent(identity, damage),
+                    debuffed_by_support: percent(debuff, damage),
+                })
+            })
+            .collect();
+        report.buff_uptime.sort_by(|a, b| b.damage.cmp(&a.damage));
+
+        report.counters = encounter
+            .entities
+            .values()
+            .filter(|e| e.entity_type == EntityType::PLAYER)
+            .map(|e| e.skill_stats.counters)
+            .sum();
+
+        report.missed_counters = self
+            .boss_casts
+            .iter()
+            .filter(|cast| !cast.countered && self.counterable_skills.contains(&cast.skill_id))
+            .map(|cast| WipeMissedCounter {
+                skill_id: cast.skill_id,
+                skill_name: get_skill_name(&cast.skill_id),
+                time: cast.timestamp - encounter.fight_start,
+            })
+            .collect();
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOSS_ID: u64 = 100;
+
+    fn encounter(boss_name: &str) -> Encounter {
+        let mut encounter = Encounter {
+            fight_start: 1_000,
+            last_combat_packet: 61_000,
+            current_boss_name: boss_name.to_string(),
+            ..Default::default()
+        };
+        encounter.insert_entity(
+            boss_name.to_string(),
+            EncounterEntity {
+                id: BOSS_ID,
+                name: boss_name.to_string(),
+                entity_type: EntityType::BOSS,
+                max_hp: 1_000,
+                current_hp: 250,
+                ..Default::default()
+            },
+        );
+        encounter
+    }
+
+    fn hp_log(first_seen: &[(&str, i32)]) -> HashMap<String, Vec<BossHpLog>> {
+        first_seen
+            .iter()
+            .map(|&(name, time)| (name.to_string(), vec![BossHpLog::new(time, 1_000, 1.0)]))
+            .collect()
+    }
+
+    #[test]
+    fn phase_follows_the_order_bosses_showed_up() {
+        let tracker = WipeTracker::new();
+        let log = hp_log(&[("Second", 30), ("First", 0), ("Third", 90)]);
+
+        let report = tracker.build_report(&encounter("Second"), &log, None, 61_000);
+        assert_eq!(report.phase, 2);
+        let report = tracker.build_report(&encounter("Third"), &log, None, 61_000);
+        assert_eq!(report.phase, 3);
+    }
+
+    #[test]
+    fn boss_without_hp_log_counts_as_the_last_phase() {
+        let tracker = WipeTracker::new();
+        let log = hp_log(&[("First", 0), ("Second", 30)]);
+
+        let report = tracker.build_report(&encounter("Unknown"), &log, None, 61_000);
+        assert_eq!(report.phase, 2);
+        let report = tracker.build_report(&encounter("Unknown"), &HashMap::new(), None, 61_000);
+        assert_eq!(report.phase, 0);
+        assert_eq!(report.hp_percent, 25.0);
+    }
+
+    #[test]
+    fn deaths_are_ordered_by_time_with_their_killing_hits() {
+        let mut tracker = WipeTracker::new();
+        tracker.on_hit_taken(1, BOSS_ID, 10, 20_000);
+        tracker.on_hit_taken(2, BOSS_ID, 10, 8_000);
+        tracker.on_hit_taken(3, BOSS_ID, 20, 1_000);
+        tracker.on_death(1, "late", 102, 21_000);
+        tracker.on_death(2, "early", 102, 9_000);
+        // last hit is too long before the death to blame it
+        tracker.on_death(3, "unrelated", 102, 15_000);
+
+        let report = tracker.build_report(&encounter("Boss"), &HashMap::new(), None, 61_000);
+        let deaths: Vec<(&str, i64)> = report
+            .deaths
+            .iter()
+            .map(|death| (death.name.as_str(), death.time))
+            .collect();
+        assert_eq!(
+            deaths,
+            vec![("early", 8_000), ("unrelated", 14_000), ("late", 20_000)]
+        );
+        assert_eq!(report.deaths[0].skill_id, Some(10));
+        assert_eq!(report.deaths[0].source.as_deref(), Some("Boss"));
+        assert_eq!(report.deaths[1].skill_id, None);
+
+        assert_eq!(report.killing_skills.len(), 1);
+        assert_eq!(report.killing_skills[0].skill_id, 10);
+        assert_eq!(report.killing_skills[0].deaths, 2);
+    }
+
+    #[test]
+    fn counters_use_the_packet_timestamp() {
+        let mut tracker = WipeTracker::new();
+        tracker.on_boss_skill(30, 10_000);
+        tracker.on_counter(12_000);
+        tracker.on_boss_skill(30, 40_000);
+        tracker.on_boss_skill(40, 45_000);
+
+        let report = tracker.build_report(&encounter("Boss"), &HashMap::new(), None, 50_000);
+        assert_eq!(report.recent_counters, 0);
+        assert_eq!(report.missed_counters.len(), 1);
+        assert_eq!(report.missed_counters[0].skill_id, 30);
+        assert_eq!(report.missed_counters[0].time, 39_000);
+
+        let report = tracker.build_report(&encounter("Boss"), &HashMap::new(), None, 20_000);
+        assert_eq!(report.recent_counters, 1);
+    }
+}
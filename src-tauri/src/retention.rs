@@ -0,0 +1,280 @@
+use crate::parser::models::*;
+use log::info;
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection};
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+// moves every log matched by a rule to the trash, returns how many were moved
+pub fn apply_retention(
+    conn: &Connection,
+    settings: &RetentionSettings,
+    now: i64,
+) -> Result<usize, rusqlite::Error> {
+    if !settings.enabled || settings.rules.is_empty() {
+        return Ok(0);
+    }
+
+    let favorite_filter = if settings.keep_favorites {
+        "AND favorite = 0"
+    } else {
+        ""
+    };
+    let personal_best_filter = if settings.keep_personal_bests {
+        "AND id NOT IN (SELECT encounter_id FROM personal_best)"
+    } else {
+        ""
+    };
+
+    let mut trashed = 0;
+    for rule in settings.rules.iter() {
+        let (select, mut params) = rule_query(rule, now);
+        let query = format!(
+            "
+        UPDATE encounter_preview
+        SET deleted_at = ?
+        WHERE deleted_at IS NULL {} {}
+            AND id IN ({})
+        ",
+            favorite_filter, personal_best_filter, select
+        );
+        params.insert(0, Value::Integer(now));
+
+        let count = conn.execute(&query, params_from_iter(params))?;
+        if count > 0 {
+            info!("retention rule {:?} moved {} encounters to trash", rule, count);
+        }
+        trashed += count;
+    }
+
+    Ok(trashed)
+}
+
+// select of the ids a rule would delete, with its params
+fn rule_query(rule: &RetentionRule, now: i64) -> (String, Vec<Value>) {
+    match rule {
+        RetentionRule::OlderThan {
+            days,
+            uncleared_only,
+            boss,
+        } => {
+            let mut params = vec![Value::Integer(now - *days as i64 * DAY_MS)];
+            let cleared_filter = if *uncleared_only { "AND NOT cleared" } else { "" };
+            let boss_filter = match boss {
+                Some(boss) => {
+                    params.push(Value::Text(boss.clone()));
+                    "AND current_boss = ?"
+                }
+                None => "",
+            };
+            let select = format!(
+                "SELECT id FROM encounter_preview WHERE fight_start < ? {} {}",
+                cleared_filter, boss_filter
+            );
+            (select, params)
+        }
+        RetentionRule::KeepBest { count, boss } => {
+            let mut params = vec![];
+            let boss_filter = match boss {
+                Some(boss) => {
+                    params.push(Value::Text(boss.clone()));
+                    "AND current_boss = ?"
+                }
+                None => "",
+            };
+            params.push(Value::Integer(*count as i64));
+            // trash fights without a boss are left to the other rules
+            let select = format!(
+                "
+            SELECT id FROM (
+                SELECT id,
+                    ROW_NUMBER() OVER (
+                        PARTITION BY current_boss, difficulty
+                        ORDER BY my_dps DESC
+                    ) AS rank
+                FROM encounter_preview
+                WHERE deleted_at IS NULL AND current_boss != '' {}
+            )
+            WHERE rank > ?
+            ",
+                boss_filter
+            );
+            (select, params)
+        }
+        RetentionRule::ShorterThan { seconds } => (
+            "SELECT id FROM encounter_preview WHERE duration < ?".to_string(),
+            vec![Value::Integer(*seconds as i64 * 1000)],
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 100 * DAY_MS;
+
+    // id, days ago, boss, duration in seconds, my dps, favorite, cleared
+    type Log = (i64, i64, &'static str, i64, i64, bool, bool);
+
+    fn setup(logs: &[Log]) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "
+            CREATE TABLE encounter_preview (
+                id INTEGER PRIMARY KEY,
+                fight_start INTEGER,
+                current_boss TEXT,
+                duration INTEGER,
+                difficulty TEXT,
+                my_dps INTEGER,
+                favorite BOOLEAN NOT NULL DEFAULT 0,
+                cleared BOOLEAN,
+                deleted_at INTEGER
+            );
+            CREATE TABLE personal_best (encounter_id INTEGER NOT NULL);
+            ",
+        )
+        .unwrap();
+        for (id, days_ago, boss, duration, my_dps, favorite, cleared) in logs {
+            conn.execute(
+                "
+                INSERT INTO encounter_preview
+                    (id, fight_start, current_boss, duration, difficulty, my_dps, favorite, cleared)
+                VALUES (?, ?, ?, ?, 'Hard', ?, ?, ?)
+                ",
+                rusqlite::params![
+                    id,
+                    NOW - days_ago * DAY_MS,
+                    boss,
+                    duration * 1000,
+                    my_dps,
+                    favorite,
+                    cleared
+                ],
+            )
+            .unwrap();
+        }
+        conn
+    }
+
+    fn trashed(conn: &Connection) -> Vec<i64> {
+        conn.prepare("SELECT id FROM encounter_preview WHERE deleted_at IS NOT NULL ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<Vec<i64>, _>>()
+            .unwrap()
+    }
+
+    fn settings(rules: Vec<RetentionRule>) -> RetentionSettings {
+        RetentionSettings {
+            enabled: true,
+            rules,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn older_than_keeps_favorites_and_personal_bests() {
+        let conn = setup(&[
+            (1, 40, "Boss", 300, 10, false, true),
+            (2, 40, "Boss", 300, 10, true, true),
+            (3, 40, "Boss", 300, 10, false, true),
+            (4, 10, "Boss", 300, 10, false, true),
+        ]);
+        conn.execute("INSERT INTO personal_best VALUES (3)", [])
+            .unwrap();
+        let rule = RetentionRule::OlderThan {
+            days: 30,
+            uncleared_only: false,
+            boss: None,
+        };
+
+        assert_eq!(
+            apply_retention(&conn, &settings(vec![rule]), NOW).unwrap(),
+            1
+        );
+        assert_eq!(trashed(&conn), vec![1]);
+    }
+
+    #[test]
+    fn older_than_filters_by_boss_and_clear() {
+        let conn = setup(&[
+            (1, 40, "Boss", 300, 10, false, false),
+            (2, 40, "Boss", 300, 10, false, true),
+            (3, 40, "Other", 300, 10, false, false),
+        ]);
+        let rule = RetentionRule::OlderThan {
+            days: 30,
+            uncleared_only: true,
+            boss: Some("Boss".to_string()),
+        };
+
+        apply_retention(&conn, &settings(vec![rule]), NOW).unwrap();
+        assert_eq!(trashed(&conn), vec![1]);
+    }
+
+    #[test]
+    fn keep_best_trashes_the_rest_per_boss() {
+        let conn = setup(&[
+            (1, 1, "Boss", 300, 30, false, true),
+            (2, 1, "Boss", 300, 10, false, true),
+            (3, 1, "Boss", 300, 20, false, true),
+            (4, 1, "Other", 300, 5, false, true),
+            (5, 1, "", 300, 1, false, false),
+            (6, 1, "", 300, 2, false, false),
+        ]);
+        let rule = RetentionRule::KeepBest {
+            count: 2,
+            boss: None,
+        };
+
+        apply_retention(&conn, &settings(vec![rule]), NOW).unwrap();
+        assert_eq!(trashed(&conn), vec![2]);
+    }
+
+    #[test]
+    fn shorter_than_uses_seconds() {
+        let conn = setup(&[
+            (1, 1, "Boss", 20, 10, false, false),
+            (2, 1, "Boss", 40, 10, false, false),
+        ]);
+        let rule = RetentionRule::ShorterThan { seconds: 30 };
+
+        apply_retention(&conn, &settings(vec![rule]), NOW).unwrap();
+        assert_eq!(trashed(&conn), vec![1]);
+    }
+
+    #[test]
+    fn disabled_settings_trash_nothing() {
+        let conn = setup(&[(1, 40, "Boss", 20, 10, false, false)]);
+        let settings = RetentionSettings {
+            enabled: false,
+            ..settings(vec![RetentionRule::ShorterThan { seconds: 30 }])
+        };
+
+        assert_eq!(apply_retention(&conn, &settings, NOW).unwrap(), 0);
+        assert!(trashed(&conn).is_empty());
+    }
+
+    #[test]
+    fn rule_query_binds_one_param_per_placeholder() {
+        let rules = [
+            RetentionRule::OlderThan {
+                days: 1,
+                uncleared_only: true,
+                boss: Some("Boss".to_string()),
+            },
+            RetentionRule::KeepBest {
+                count: 1,
+                boss: Some("Boss".to_string()),
+            },
+            RetentionRule::ShorterThan { seconds: 1 },
+        ];
+        for rule in rules.iter() {
+            let (select, params) = rule_query(rule, NOW);
+            assert_eq!(select.matches('?').count(), params.len(), "{:?}", rule);
+        }
+    }
+}
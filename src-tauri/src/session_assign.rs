@@ -0,0 +1,134 @@
+use crate::parser::models::*;
+use hashbrown::HashSet;
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection, OptionalExtension};
+
+// a break longer than this between pulls ends the session
+const SESSION_GAP_MS: i64 = 30 * 60 * 1000;
+// share of the smaller roster that has to carry over for pulls to count as the same group
+const MIN_ROSTER_OVERLAP: f64 = 0.5;
+
+struct SessionEnd {
+    session_id: i64,
+    end: i64,
+    roster: HashSet<String>,
+}
+
+// everyone in the raid from party info, the preview players for logs saved without it
+pub(crate) fn get_session_roster(misc: Option<&EncounterMisc>, players: &str) -> HashSet<String> {
+    if let Some(party_info) = misc.and_then(|m| m.party_info.as_ref()) {
+        if !party_info.is_empty() {
+            return party_info.values().flatten().cloned().collect();
+        }
+    }
+
+    players
+        .split(',')
+        .filter_map(|player| player.split_once(':').map(|(_, name)| name.to_string()))
+        .collect()
+}
+
+// misc is stored as plain json, unlike the gzipped stat columns
+fn read_misc(value: ValueRef) -> Option<EncounterMisc> {
+    match value {
+        ValueRef::Text(text) => serde_json::from_slice(text).ok(),
+        _ => None,
+    }
+}
+
+fn is_same_session(previous: &SessionEnd, fight_start: i64, roster: &HashSet<String>) -> bool {
+    if fight_start - previous.end > SESSION_GAP_MS {
+        return false;
+    }
+    // nothing to compare against, go by time alone
+    if previous.roster.is_empty() || roster.is_empty() {
+        return true;
+    }
+
+    let shared = previous.roster.intersection(roster).count();
+    shared as f64 >= previous.roster.len().min(roster.len()) as f64 * MIN_ROSTER_OVERLAP
+}
+
+// continues the session of the previous encounter when it's close enough in time and
+// mostly the same group, otherwise starts a new session keyed by this encounter
+pub(crate) fn assign_session(
+    conn: &Connection,
+    encounter_id: i64,
+    fight_start: i64,
+    roster: &HashSet<String>,
+) -> Result<i64, rusqlite::Error> {
+    let previous = conn
+        .query_row(
+            "
+    SELECT ep.session_id, ep.fight_start + ep.duration, ep.players, e.misc
+    FROM encounter_preview ep
+    JOIN encounter e USING (id)
+    WHERE ep.id != ?1 AND ep.fight_start <= ?2 AND ep.session_id IS NOT NULL
+    ORDER BY ep.fight_start DESC
+    LIMIT 1
+    ",
+            params![encounter_id, fight_start],
+            |row| {
+                let misc = read_misc(row.get_ref(3)?);
+                let players: String = row.get(2).unwrap_or_default();
+                Ok(SessionEnd {
+                    session_id: row.get(0)?,
+                    end: row.get(1)?,
+                    roster: get_session_roster(misc.as_ref(), &players),
+                })
+            },
+        )
+        .optional()?;
+
+    let session_id = match previous {
+        Some(previous) if is_same_session(&previous, fight_start, roster) => previous.session_id,
+        _ => encounter_id,
+    };
+    conn.execute(
+        "UPDATE encounter_preview SET session_id = ? WHERE id = ?",
+        params![session_id, encounter_id],
+    )?;
+
+    Ok(session_id)
+}
+
+// groups every saved encounter into sessions, used when the column is first added
+pub(crate) fn backfill_sessions(conn: &Connection) -> Result<(), rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "
+    SELECT ep.id, ep.fight_start, ep.duration, ep.players, e.misc
+    FROM encounter_preview ep
+    JOIN encounter e USING (id)
+    ORDER BY ep.fight_start
+    ",
+    )?;
+    let mut update_stmt =
+        conn.prepare("UPDATE encounter_preview SET session_id = ? WHERE id = ?")?;
+
+    let mut previous: Option<SessionEnd> = None;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let encounter_id: i64 = row.get(0)?;
+        let fight_start: i64 = row.get(1)?;
+        let duration: i64 = row.get(2)?;
+        let players: String = row.get(3).unwrap_or_default();
+        let misc = read_misc(row.get_ref(4)?);
+        let roster = get_session_roster(misc.as_ref(), &players);
+
+        let session_id = match &previous {
+            Some(previous) if is_same_session(previous, fight_start, &roster) => {
+                previous.session_id
+            }
+            _ => encounter_id,
+        };
+        update_stmt.execute(params![session_id, encounter_id])?;
+
+        previous = Some(SessionEnd {
+            session_id,
+            end: fight_start + duration,
+            roster,
+        });
+    }
+
+    Ok(())
+}
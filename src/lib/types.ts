@@ -3,6 +3,11 @@ export interface EncounterEvent {
     payload: Encounter;
 }
 
+export interface BossForecastEvent {
+    event: string;
+    payload: BossHpForecast;
+}
+
 export interface PartyEvent {
     event: string;
     payload?: PartyInfo;
@@ -23,6 +28,8 @@ export interface Encounter {
     cleared: boolean;
     bossOnlyDamage: boolean;
     sync?: string;
+    raid?: string;
+    gate?: number;
 }
 
 export interface EncountersOverview {
@@ -42,6 +49,8 @@ export interface EncounterPreview {
     myDps: number;
     favorite: boolean;
     cleared: boolean;
+    raid?: string;
+    gate?: number;
 }
 
 export interface EncounterDamageStats {
@@ -67,6 +76,7 @@ export interface EncounterMisc {
     partyInfo?: PartyInfo;
     rdpsValid?: boolean;
     rdpsMessage?: string;
+    wipeReport?: WipeReport;
 }
 
 export interface PartyInfo {
@@ -85,6 +95,19 @@ export class BossHpLog {
     }
 }
 
+export interface BossHpForecast {
+    bossName: string;
+    currentHp: number;
+    maxHp: number;
+    dps: number;
+    elapsed: number;
+    timeToKill?: number;
+    predictedKillTime?: number;
+    enrageTime?: number;
+    requiredDps?: number;
+    confidence: number;
+}
+
 export interface StaggerStats {
     log: Array<[number, number]>;
     average: number;
@@ -470,6 +493,7 @@ export class SearchFilter {
     bossOnlyDamage: boolean;
     sort: string;
     order: number;
+    trashed: boolean;
 
     constructor(minDuration = -1) {
         this.bosses = new Set();
@@ -481,6 +505,7 @@ export class SearchFilter {
         this.bossOnlyDamage = false;
         this.sort = "id";
         this.order = 2;
+        this.trashed = false;
     }
 }
 
@@ -490,3 +515,321 @@ export interface PartyBuffs {
     partyPercentages: Array<number[]>;
     partyBuffs: Map<string, Map<string, Array<BuffDetails>>>;
 }
+
+export interface EncounterComparison {
+    left: ComparedEncounter;
+    right: ComparedEncounter;
+    players: Array<PlayerComparison>;
+    unmatchedLeft: Array<string>;
+    unmatchedRight: Array<string>;
+}
+
+export interface ComparedEncounter {
+    id: string;
+    bossName: string;
+    difficulty?: string;
+    raid?: string;
+    gate?: number;
+    fightStart: number;
+    duration: number;
+    cleared: boolean;
+    dps: number;
+}
+
+export interface PlayerComparison {
+    matchedBy: "name" | "class";
+    leftName: string;
+    rightName: string;
+    class: string;
+    left: PlayerSnapshot;
+    right: PlayerSnapshot;
+    diff: PlayerSnapshot;
+    skills: Array<SkillComparison>;
+}
+
+export interface PlayerSnapshot {
+    damage: number;
+    dps: number;
+    damagePercent: number;
+    critRate: number;
+    backAttackRate: number;
+    frontAttackRate: number;
+    buffedBySupportPercent: number;
+    buffedByIdentityPercent: number;
+    buffedByHatPercent: number;
+    debuffedBySupportPercent: number;
+    rdpsDamageReceived: number;
+    rdpsDamageReceivedSupport: number;
+    rdpsDamageGiven: number;
+    casts: number;
+    hits: number;
+    deaths: number;
+}
+
+export interface SkillComparison {
+    id: number;
+    name: string;
+    icon: string;
+    left?: SkillSnapshot;
+    right?: SkillSnapshot;
+    diff?: SkillSnapshot;
+}
+
+export interface SkillSnapshot {
+    damage: number;
+    dps: number;
+    damagePercent: number;
+    maxDamage: number;
+    casts: number;
+    hits: number;
+    critRate: number;
+    buffedBySupportPercent: number;
+    buffedByIdentityPercent: number;
+    debuffedBySupportPercent: number;
+    rdpsDamageReceived: number;
+    rdpsDamageGiven: number;
+    castsPerMinute: number;
+    averageCastInterval: number;
+}
+
+export interface PersonalBest {
+    name: string;
+    classId: number;
+    class: string;
+    boss: string;
+    difficulty: string;
+    kind: "dps" | "rdps" | "clear_time" | "max_hit";
+    value: number;
+    previous?: number;
+    skillId?: number;
+    encounterId: number;
+    fightStart: number;
+}
+
+export interface PlayerRanking {
+    name: string;
+    classId: number;
+    class: string;
+    spec?: string;
+    gearScore: number;
+    dps: number;
+    rank: number;
+    total: number;
+    percentile: number;
+}
+
+export interface CharacterTrends {
+    name: string;
+    classId: number;
+    class: string;
+    bosses: Array<BossTrend>;
+    specChanges: Array<SpecChange>;
+}
+
+export interface BossTrend {
+    boss: string;
+    difficulty?: string;
+    weeks: Array<WeeklyTrend>;
+}
+
+export interface WeeklyTrend {
+    weekStart: number;
+    encounters: number;
+    averageDps: number;
+    bestDps: number;
+    gearScore: number;
+    critRate: number;
+    buffedBySupportPercent: number;
+    deaths: number;
+}
+
+export interface SpecChange {
+    encounterId: number;
+    fightStart: number;
+    previousSpec?: string;
+    spec?: string;
+    arkPassiveChanged: boolean;
+    arkPassiveData?: ArkPassiveData;
+}
+
+export interface RosterCharacter {
+    characterId: number;
+    name: string;
+    classId: number;
+    class: string;
+    gearScore: number;
+    lastSeen: number;
+    count: number;
+    clears: Array<RosterClear>;
+    pending: Array<RosterClear>;
+}
+
+export interface RosterClear {
+    raid: string;
+    gate: number;
+    difficulty: string;
+    encounterId?: number;
+    fightStart?: number;
+}
+
+export interface WeeklyClears {
+    region?: string;
+    resetStart: number;
+    nextReset: number;
+    characters: Array<CharacterClears>;
+}
+
+export interface CharacterClears {
+    name: string;
+    classId: number;
+    clears: Array<WeeklyClear>;
+}
+
+export interface WeeklyClear {
+    boss: string;
+    raid?: string;
+    gate?: number;
+    difficulty: string;
+    encounterId: number;
+    fightStart: number;
+    clears: number;
+    firstClear: boolean;
+}
+
+export interface BossProgression {
+    boss: string;
+    difficulty?: string;
+    raid?: string;
+    gate?: number;
+    pulls: number;
+    bestHpPercent: number;
+    bestPhase: number;
+    bestEncounterId?: number;
+    totalDeaths: number;
+    averageDeaths: number;
+    totalTime: number;
+    nights: Array<ProgNight>;
+    curve: Array<ProgPull>;
+}
+
+export interface ProgNight {
+    night: number;
+    pulls: number;
+    combatTime: number;
+    firstPullStart: number;
+    lastPullEnd: number;
+    bestHpPercent: number;
+    deaths: number;
+}
+
+export interface ProgPull {
+    pull: number;
+    encounterId: number;
+    fightStart: number;
+    duration: number;
+    hpPercent: number;
+    phase: number;
+    phaseBoss: string;
+    deaths: number;
+}
+
+export interface WipeReport {
+    bossName: string;
+    bossHp: number;
+    bossMaxHp: number;
+    hpPercent: number;
+    phase: number;
+    wipeTime: number;
+    deaths: Array<WipeDeath>;
+    killingSkills: Array<WipeKillingSkill>;
+    buffUptime: Array<WipeBuffUptime>;
+    staggerCheckMissed?: boolean;
+    counters: number;
+    recentCounters: number;
+    missedCounters: Array<WipeMissedCounter>;
+}
+
+export interface WipeDeath {
+    name: string;
+    classId: number;
+    time: number;
+    skillId?: number;
+    skillName?: string;
+    source?: string;
+}
+
+export interface WipeKillingSkill {
+    source: string;
+    skillId: number;
+    skillName: string;
+    deaths: number;
+}
+
+export interface WipeMissedCounter {
+    skillId: number;
+    skillName: string;
+    time: number;
+}
+
+export interface WipeBuffUptime {
+    name: string;
+    classId: number;
+    damage: number;
+    buffedBySupport: number;
+    buffedByIdentity: number;
+    debuffedBySupport: number;
+}
+
+export interface RaidSession {
+    id: number;
+    start: number;
+    end: number;
+    encounters: number;
+    totalTime: number;
+    combatTime: number;
+    downtime: number;
+    gatesCleared: number;
+    wipes: number;
+    players: Array<SessionPlayer>;
+    pulls: Array<SessionPull>;
+}
+
+export interface SessionPlayer {
+    name: string;
+    classId: number;
+    class: string;
+    encounters: number;
+    clears: number;
+    combatTime: number;
+    damage: number;
+    rdpsDamage: number;
+    dps: number;
+    rdps: number;
+    deaths: number;
+}
+
+export interface SessionPull {
+    encounterId: number;
+    boss: string;
+    difficulty?: string;
+    fightStart: number;
+    duration: number;
+    cleared: boolean;
+}
+
+export interface DbBackup {
+    name: string;
+    kind: string;
+    created: number;
+    size: number;
+}
+
+export interface AppError {
+    kind: "notFound" | "corrupt" | "locked" | "io";
+    message: string;
+}
+
+export type RetentionRule =
+    | { kind: "olderThan"; days: number; unclearedOnly?: boolean; boss?: string }
+    | { kind: "keepBest"; count: number; boss?: string }
+    | { kind: "shorterThan"; seconds: number };
@@ -0,0 +1,6 @@
+import type { Encounter, Entity } from "$lib/types";
+
+// entities are keyed by character/npc id, look them up by display name
+export function findEntity(encounter: Encounter, name: string): Entity | undefined {
+    return Object.values(encounter.entities).find((entity) => entity.name === name);
+}
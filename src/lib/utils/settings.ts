@@ -44,7 +44,8 @@ export const defaultSettings = {
         constantLocalPlayerColor: false,
         bossOnlyDamageDefaultOn: true,
         startOnBoot: false,
-        logsPerPage: 10
+        logsPerPage: 10,
+        weeklyTraySummary: false
     },
     shortcuts: {
         hideMeter: {
@@ -141,6 +142,8 @@ export const defaultSettings = {
         backAtk: true,
         counters: false,
         minEncounterDuration: 30,
+        trashRetentionDays: 30,
+        archiveAfterDays: 0,
         positionalDmgPercent: true,
         percentBuffBySup: false,
         percentIdentityBySup: false,
@@ -185,6 +188,17 @@ export const defaultSettings = {
         auto: false,
         username: "",
         visibility: "0"
+    },
+    backup: {
+        enabled: true,
+        intervalHours: 24,
+        keep: 5
+    },
+    retention: {
+        enabled: false,
+        keepFavorites: true,
+        keepPersonalBests: true,
+        rules: []
     }
 };
 
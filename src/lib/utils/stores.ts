@@ -15,6 +15,8 @@ export const backNavStore = writable(false);
 export const ifaceChangedStore = writable(false);
 export const uploadErrorStore = writable(false);
 export const uploadErrorMessage = writable("");
+export const dbErrorStore = writable(false);
+export const dbErrorMessage = writable("");
 
 export const searchFilter = writable(new SearchFilter());
 
@@ -1,5 +1,5 @@
 import { estherMap } from "$lib/constants/esthers";
-import type { Entity } from "$lib/types";
+import type { AppError, Entity } from "$lib/types";
 import { round2 } from "./numbers";
 import { missingInfo } from "./stores";
 
@@ -62,6 +62,23 @@ export function getEstherFromNpcId(npcId: number): string {
     return "Unknown";
 }
 
+export function formatAppError(error: unknown): string {
+    const appError = error as AppError;
+    if (!appError?.kind) {
+        return String(error);
+    }
+    switch (appError.kind) {
+        case "notFound":
+            return "Not found: " + appError.message;
+        case "corrupt":
+            return "Corrupt data: " + appError.message;
+        case "locked":
+            return "Database is busy, try again: " + appError.message;
+        default:
+            return "Error: " + appError.message;
+    }
+}
+
 // this is used to invalidate caches when loading images
 // change this value when images are updated
 export const queryParam: string = "?194";
@@ -1,11 +1,21 @@
 import { invoke } from "@tauri-apps/api/tauri";
+import { redirect } from "@sveltejs/kit";
 import type { Encounter } from "$lib/types";
+import { dbErrorMessage, dbErrorStore } from "$lib/utils/stores";
+import { formatAppError } from "$lib/utils/strings";
 import type { PageLoad } from "./$types";
 
 export const prerender: boolean = false;
 
 export const load: PageLoad = async ({ params }) => {
-    // If there's no such id this return `Encounter` with all fields zeroed
-    const encounter = (await invoke("load_encounter", { id: params.id })) as Encounter;
+    let encounter: Encounter;
+    try {
+        encounter = (await invoke("load_encounter", { id: params.id })) as Encounter;
+    } catch (e) {
+        // missing or unreadable logs go back to the list with the error shown there
+        dbErrorMessage.set(formatAppError(e));
+        dbErrorStore.set(true);
+        redirect(307, "/logs");
+    }
     return { id: params.id, encounter };
 };